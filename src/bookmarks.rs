@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use crate::state::{document_path, read_document, store_json};
+
+/// A named position in a document.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub section_index: usize,
+    pub word_index: usize,
+}
+
+/// The bookmarks of a document, kept in reading order and stored next to its state.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    identifier: String,
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn load(state_dir: &Path, identifier: String) -> Self {
+        let bookmarks = read_document(state_dir, &identifier, Some("bookmarks"))
+            .and_then(|b| serde_json::from_str(&b).ok())
+            .unwrap_or_default();
+        Self {
+            identifier,
+            bookmarks,
+        }
+    }
+
+    pub fn store(&self, state_dir: &Path) -> anyhow::Result<()> {
+        let path = document_path(state_dir, &self.identifier, Some("bookmarks"));
+        store_json(&path, &self.bookmarks)
+    }
+
+    pub fn list(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    /// Adds a bookmark and returns its position in the list.
+    pub fn add(&mut self, bookmark: Bookmark) -> usize {
+        let position = self.bookmarks.partition_point(|b| {
+            (b.section_index, b.word_index) <= (bookmark.section_index, bookmark.word_index)
+        });
+        self.bookmarks.insert(position, bookmark);
+        position
+    }
+
+    pub fn rename(&mut self, index: usize, name: String) {
+        if let Some(bookmark) = self.bookmarks.get_mut(index) {
+            bookmark.name = name;
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.bookmarks.len() {
+            self.bookmarks.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_keeps_bookmarks_in_reading_order() {
+        let mut bookmarks = Bookmarks::default();
+
+        check!(bookmarks.add(bookmark("later", 3, 10)) == 0);
+        check!(bookmarks.add(bookmark("first", 1, 200)) == 0);
+        check!(bookmarks.add(bookmark("same section", 3, 2)) == 1);
+        bookmarks.rename(2, "last".to_string());
+        bookmarks.remove(0);
+
+        let names: Vec<_> = bookmarks.list().iter().map(|b| b.name.as_str()).collect();
+        check!(names == vec!["same section", "last"]);
+    }
+
+    #[rstest]
+    fn it_stores_bookmarks() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut bookmarks = Bookmarks::load(dir, "book".to_string());
+        check!(bookmarks.list().is_empty());
+
+        bookmarks.add(bookmark("quote", 2, 42));
+        bookmarks.store(dir).unwrap();
+
+        let bookmarks = Bookmarks::load(dir, "book".to_string());
+        check!(bookmarks.list() == [bookmark("quote", 2, 42)]);
+    }
+
+    fn bookmark(name: &str, section_index: usize, word_index: usize) -> Bookmark {
+        Bookmark {
+            name: name.to_string(),
+            section_index,
+            word_index,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::Context;
+
+use crate::{
+    keymap::Keymap,
+    theme::{Preset, Theme, ThemeConfig},
+};
+
+/// Settings read from `$XDG_CONFIG_HOME/word-runner/config.toml`, all of them optional.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Keys bound to actions, replacing their default ones, like `toc-up = ["k", "ctrl-p"]`.
+    keys: HashMap<String, Keys>,
+    /// Colors, like `preset = "light"` with `pivot = "#dc322f"`.
+    theme: Option<ThemeConfig>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Config {
+    pub fn load() -> anyhow::Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .with_context(|| format!("invalid configuration in {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("unable to read {}", path.display())),
+        }
+    }
+
+    fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("word-runner").join("config.toml"))
+    }
+
+    pub fn keymap(&self) -> anyhow::Result<Keymap> {
+        let overrides = self
+            .keys
+            .iter()
+            .map(|(action, keys)| {
+                let keys = match keys {
+                    Keys::One(key) => vec![key.clone()],
+                    Keys::Many(keys) => keys.clone(),
+                };
+                (action.clone(), keys)
+            })
+            .collect();
+        Keymap::new(&overrides).context("invalid key binding")
+    }
+
+    /// The configured theme; without one, `NO_COLOR` asks for no colors at all.
+    pub fn theme(&self, no_color: bool) -> anyhow::Result<Theme> {
+        match &self.theme {
+            Some(theme) => theme.theme().context("invalid theme"),
+            None if no_color => Ok(Theme::preset(Preset::Monochrome)),
+            None => Ok(Theme::preset(Preset::Dark)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use rstest::*;
+
+    use crate::keymap::{Action, Mode};
+
+    #[rstest]
+    fn it_reads_key_bindings() {
+        let config: Config = toml::from_str(
+            r#"
+            [keys]
+            toc-up = "k"
+            toc-down = ["j", "ctrl-n"]
+            "#,
+        )
+        .unwrap();
+
+        let keymap = config.keymap().unwrap();
+        let key = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        check!(keymap.action(Mode::Reading, key) == Some(Action::TocDown));
+    }
+
+    #[rstest]
+    #[case("", false, Preset::Dark)]
+    #[case("", true, Preset::Monochrome)]
+    #[case("[theme]\npreset = \"solarized\"", true, Preset::Solarized)]
+    fn it_picks_the_theme(#[case] config: &str, #[case] no_color: bool, #[case] preset: Preset) {
+        let config: Config = toml::from_str(config).unwrap();
+
+        check!(config.theme(no_color).unwrap() == Theme::preset(preset));
+    }
+
+    #[rstest]
+    fn it_rejects_unknown_settings() {
+        check!(toml::from_str::<Config>("speed = 3").is_err());
+    }
+}
@@ -1,14 +1,92 @@
-use std::{
-    fs::File,
-    io::{BufReader, BufWriter, Write},
-    ops::{Deref, DerefMut},
-    path::Path,
-    usize,
-};
-
-use anyhow::Result;
-use epub::doc::NavPoint;
+use std::{io::Read, ops::Range, path::Path};
+
+use anyhow::{bail, Result};
+use html2text::render::text_renderer::{PlainDecorator, TaggedLine, TextDecorator};
 use itertools::Itertools;
+use sha2::{Digest, Sha256};
+use unicode_segmentation::UnicodeSegmentation;
+
+mod epub;
+mod fb2;
+mod html;
+mod markdown;
+mod mobi;
+mod office;
+mod pdf;
+mod text;
+mod words;
+
+pub use epub::EpubDoc;
+pub use fb2::Fb2Doc;
+pub use html::HtmlDoc;
+pub use markdown::MarkdownDoc;
+pub use mobi::MobiDoc;
+pub use office::OfficeDoc;
+pub use pdf::PdfDoc;
+pub use text::TextDoc;
+pub use words::{is_cjk, word_ranges};
+
+/// A readable document split into sections, independent of its on-disk format.
+pub trait Document {
+    /// Number of sections the document is made of.
+    fn sections(&self) -> usize;
+    /// Raw HTML content of the section at `index`.
+    fn section(&mut self, index: usize) -> Option<Vec<u8>>;
+    fn table_of_contents(&self) -> &[TableOfContentNode];
+    /// Identifier used to store and restore the reading state.
+    fn identifier(&self) -> String;
+    fn metadata(&self) -> Metadata;
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+}
+
+/// Opens the document at `path`, picking the backend from the file extension.
+/// Directories are read as a set of HTML pages.
+pub fn open(path: &Path) -> Result<Box<dyn Document>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    if path.is_dir() {
+        return Ok(Box::new(HtmlDoc::open(path)?));
+    }
+    match extension.as_str() {
+        "epub" => Ok(Box::new(EpubDoc::open(path)?)),
+        "txt" => Ok(Box::new(TextDoc::open(path)?)),
+        "md" | "markdown" => Ok(Box::new(MarkdownDoc::open(path)?)),
+        "html" | "htm" | "xhtml" => Ok(Box::new(HtmlDoc::open(path)?)),
+        "fb2" => Ok(Box::new(Fb2Doc::open(path)?)),
+        "zip" if fb2::is_zipped(path) => Ok(Box::new(Fb2Doc::open(path)?)),
+        "mobi" | "azw" | "azw3" => Ok(Box::new(MobiDoc::open(path)?)),
+        "pdf" => Ok(Box::new(PdfDoc::open(path)?)),
+        "docx" | "odt" => Ok(Box::new(OfficeDoc::open(path)?)),
+        _ => bail!("unsupported document format: {}", path.display()),
+    }
+}
+
+/// Reads a plain text document from a stream. Its reading state is keyed by a hash
+/// of the content.
+pub fn read(input: impl Read) -> Result<Box<dyn Document>> {
+    Ok(Box::new(TextDoc::read(input)?))
+}
+
+/// Words shown before a search hit in its snippet, twice as many are shown after it.
+const SNIPPET_WORDS: usize = 5;
+
+/// A place where a search query was found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub section_index: usize,
+    pub word_index: usize,
+    /// Name of the table of contents entry of the section.
+    pub chapter: Option<String>,
+    pub snippet: String,
+}
 
 #[derive(Debug)]
 pub struct TableOfContentNode {
@@ -17,40 +95,76 @@ pub struct TableOfContentNode {
     pub children: Vec<TableOfContentNode>,
 }
 
-impl TableOfContentNode {
-    fn new(value: &NavPoint, doc: &epub::doc::EpubDoc<BufReader<File>>) -> Self {
-        Self {
-            index: doc.resource_uri_to_chapter(&value.content).unwrap(),
-            name: value.label.clone(),
-            children: value
-                .children
-                .iter()
-                .map(|t| TableOfContentNode::new(t, doc))
-                .collect(),
-        }
+/// Builds a table of contents tree out of a flat list of `(level, section index, name)` headings.
+fn toc_from_headings(headings: &[(usize, usize, String)]) -> Vec<TableOfContentNode> {
+    let mut result = vec![];
+    let mut i = 0;
+    while i < headings.len() {
+        let (level, index, name) = &headings[i];
+        let end = headings[i + 1..]
+            .iter()
+            .position(|(l, _, _)| l <= level)
+            .map(|p| i + 1 + p)
+            .unwrap_or(headings.len());
+        result.push(TableOfContentNode {
+            index: *index,
+            name: name.clone(),
+            children: toc_from_headings(&headings[i + 1..end]),
+        });
+        i = end;
     }
+    result
+}
+
+/// Identifier derived from the document content, for formats without one of their own.
+fn content_identifier(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
 pub struct DocumentCursor {
-    doc: EpubDoc,
+    doc: Box<dyn Document>,
     current_section: SectionCursor,
+    /// Number of words of every section.
+    word_counts: Vec<usize>,
 }
 
 impl DocumentCursor {
-    pub fn new(mut doc: EpubDoc, doc_state: DocState) -> Self {
-        doc.set_current_page(doc_state.section_index);
-        let mut current_section = doc
-            .get_current()
-            .map(|c| SectionCursor::new(doc.get_current_page(), c.0, 80))
-            .unwrap_or_default();
-        current_section.word_index = doc_state.word_index;
-        Self {
+    pub fn new(doc: Box<dyn Document>, doc_state: DocState) -> Self {
+        let mut cursor = Self {
             doc,
-            current_section,
+            current_section: SectionCursor::default(),
+            word_counts: doc_state.word_counts,
+        };
+        // Counted once, then kept in the reading state.
+        if cursor.word_counts.len() != cursor.sections() {
+            cursor.word_counts = (0..cursor.sections())
+                .map(|index| cursor.render(index, 80).map_or(0, |s| s.words().count()))
+                .collect();
+        }
+        if let Some(section) = cursor.render(doc_state.section_index, 80) {
+            cursor.current_section = section;
         }
+        cursor.current_section.goto_word(doc_state.word_index);
+        cursor
     }
+
+    /// Renders the section at `index`, counting its words.
+    fn render(&mut self, index: usize, size: usize) -> Option<SectionCursor> {
+        let section = SectionCursor::new(index, self.doc.section(index)?, size);
+        if let Some(count) = self.word_counts.get_mut(index) {
+            *count = section.words().count();
+        }
+        Some(section)
+    }
+
     pub fn section_index(&self) -> usize {
-        self.doc.get_current_page()
+        self.current_section.index
     }
     pub fn current_section(&mut self) -> &mut SectionCursor {
         &mut self.current_section
@@ -64,80 +178,167 @@ impl DocumentCursor {
     }
 
     pub fn goto_section(&mut self, index: usize) -> bool {
-        if self.doc.set_current_page(index) {
-            self.load_section();
+        if let Some(section) = self.render(index, self.current_section.size) {
+            let chunk_size = self.current_section.chunk_size;
+            self.current_section = section;
+            self.current_section.chunk_size = chunk_size;
             true
         } else {
             false
         }
     }
 
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.current_section.chunk_size = chunk_size;
+    }
+
     pub fn prev_section(&mut self) -> bool {
-        if self.doc.go_prev() {
-            self.load_section();
-            true
-        } else {
-            false
-        }
+        self.section_index() > 0 && self.goto_section(self.section_index() - 1)
     }
 
     pub fn next_section(&mut self) -> bool {
-        if self.doc.go_next() {
-            self.load_section();
-            true
-        } else {
-            false
-        }
+        self.goto_section(self.section_index() + 1)
     }
 
     pub fn sections(&self) -> usize {
-        self.doc.get_num_pages()
-    }
-
-    fn load_section(&mut self) {
-        self.current_section = self
-            .doc
-            .get_current()
-            .map(|c| {
-                SectionCursor::new(
-                    self.doc.get_current_page(),
-                    c.0,
-                    self.current_section().size,
-                )
-            })
-            .unwrap_or_default();
+        self.doc.sections()
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        self.doc.metadata()
     }
 
     pub fn toc_index(&self) -> Vec<usize> {
-        toc_index(&self, &self.doc.table_of_contents())
+        toc_index(self.section_index(), self.doc.table_of_contents(), None)
+    }
+
+    /// Moves to the section of the table of contents entry at the given path of sibling positions.
+    pub fn goto_toc_entry(&mut self, path: &[usize]) -> bool {
+        let mut entries = self.doc.table_of_contents();
+        let mut index = None;
+        for position in path {
+            let Some(entry) = entries.get(*position) else {
+                return false;
+            };
+            index = Some(entry.index);
+            entries = &entry.children;
+        }
+        index.is_some_and(|i| self.goto_section(i))
     }
 
-    pub fn doc_state(&self) -> DocState {
-        DocState {
-            identifier: self.doc.unique_identifier.clone().unwrap(),
-            section_index: self.current_section.index,
-            word_index: self.current_section.word_index,
+    /// Moves to the word with the given index in the given section.
+    pub fn goto_word(&mut self, section_index: usize, word_index: usize) -> bool {
+        if section_index != self.section_index() && !self.goto_section(section_index) {
+            return false;
         }
+        self.current_section.goto_word(word_index);
+        true
     }
-}
 
-fn toc_index(cursor: &DocumentCursor, toc: &[TableOfContentNode]) -> Vec<usize> {
-    let mut res = vec![];
-    if toc.is_empty() || cursor.section_index() < cursor.doc.table_of_contents()[0].index {
-        return res;
+    /// Name of the deepest table of contents entry the section belongs to.
+    pub fn chapter(&self, section_index: usize) -> Option<String> {
+        let mut entries = self.doc.table_of_contents();
+        let mut name = None;
+        for position in toc_index(section_index, entries, None) {
+            let entry = entries.get(position)?;
+            name = Some(entry.name.clone());
+            entries = &entry.children;
+        }
+        name
     }
 
-    let mut i = 0;
-    while i < toc.len() - 1 {
-        if toc[i].index >= cursor.section_index() && cursor.section_index() < toc[i + 1].index {
-            break;
+    /// Case-insensitive search of the whole document, in reading order.
+    pub fn search(&mut self, query: &str) -> Vec<SearchHit> {
+        let query = query
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let mut hits: Vec<SearchHit> = vec![];
+        if query.is_empty() {
+            return hits;
         }
-        i += 1;
+        for index in 0..self.sections() {
+            let Some(section) = self.render(index, self.current_section.size) else {
+                continue;
+            };
+            let words: Vec<&str> = section.words().collect();
+            let mut text = String::new();
+            let mut starts = vec![];
+            for word in &words {
+                starts.push(text.len());
+                text.push_str(&word.to_lowercase());
+                text.push(' ');
+            }
+            for (offset, _) in text.match_indices(&query) {
+                let word_index = starts.partition_point(|s| *s <= offset) - 1;
+                if hits
+                    .last()
+                    .is_some_and(|h| h.section_index == index && h.word_index == word_index)
+                {
+                    continue;
+                }
+                hits.push(SearchHit {
+                    section_index: index,
+                    word_index,
+                    chapter: self.chapter(index),
+                    snippet: words[word_index.saturating_sub(SNIPPET_WORDS)
+                        ..(word_index + 2 * SNIPPET_WORDS).min(words.len())]
+                        .join(" "),
+                });
+            }
+        }
+        hits
+    }
+
+    /// Number of words from the current one to the end of the document.
+    pub fn words_left(&self) -> usize {
+        let later: usize = self.word_counts.iter().skip(self.section_index() + 1).sum();
+        let current = self.current_section.words().count();
+        later + current.saturating_sub(self.current_section.word_index)
     }
-    res.push(toc[i].index);
-    if !toc[i].children.is_empty() {
-        res.extend(toc_index(cursor, &toc[i].children));
+
+    /// Fraction of the document's words before the current one.
+    pub fn progress(&self) -> f64 {
+        let total: usize = self.word_counts.iter().sum();
+        let before = self
+            .word_counts
+            .iter()
+            .take(self.section_index())
+            .sum::<usize>()
+            + self.current_section.word_index;
+        (before as f64 / total.max(1) as f64).min(1.0)
+    }
+
+    pub fn doc_state(&self) -> DocState {
+        DocState {
+            identifier: self.doc.identifier(),
+            section_index: self.current_section.index,
+            word_index: self.current_section.word_index,
+            wpm: None,
+            word_counts: self.word_counts.clone(),
+        }
     }
+}
+
+/// Path of sibling positions leading to the deepest entry starting at or before `section_index`.
+/// Entries pointing to the same section as their parent are never selected.
+fn toc_index(
+    section_index: usize,
+    toc: &[TableOfContentNode],
+    parent_index: Option<usize>,
+) -> Vec<usize> {
+    let Some((position, node)) = toc
+        .iter()
+        .enumerate()
+        .take_while(|(_, t)| t.index <= section_index)
+        .filter(|(_, t)| parent_index.is_none_or(|p| t.index > p))
+        .last()
+    else {
+        return vec![];
+    };
+    let mut res = vec![position];
+    res.extend(toc_index(section_index, &node.children, Some(node.index)));
     res
 }
 
@@ -150,18 +351,32 @@ pub struct SectionCursor {
     word_index: usize,
     line_index: usize,
     size: usize,
+    /// Number of words shown at once.
+    chunk_size: usize,
+    /// Index of the first word of every paragraph.
+    paragraphs: Vec<usize>,
+    /// Index of the first word of every sentence.
+    sentences: Vec<usize>,
 }
 
 impl SectionCursor {
     fn new(number: usize, raw_content: Vec<u8>, size: usize) -> Self {
-        let content = String::from_utf8(raw_content.clone()).unwrap();
-        let content = html2text::from_read(content.as_bytes(), size);
-        let lines = lines(content.clone());
+        let rendered = html2text::parse(raw_content.as_slice())
+            .render(size, SectionDecorator::new())
+            .into_lines();
+        let content = rendered
+            .iter()
+            .map(|l| l.clone().into_string() + "\n")
+            .collect();
+        let lines = lines(&rendered);
+        let (paragraphs, sentences) = boundaries(&lines);
+        let line_index = lines
+            .iter()
+            .position(|l| !l.word_indexes.is_empty())
+            .unwrap_or_default();
         let word_index = lines
-            .first()
-            .map(|l| l.word_indexes.first())
-            .flatten()
-            .copied()
+            .get(line_index)
+            .map(|l| l.first_word_index())
             .unwrap_or_default();
         Self {
             index: number,
@@ -169,25 +384,18 @@ impl SectionCursor {
             raw_content,
             lines,
             word_index,
-            line_index: 0,
+            line_index,
             size,
+            chunk_size: 1,
+            paragraphs,
+            sentences,
         }
     }
 
     fn from_resize(other: &Self, size: usize) -> Self {
         let mut result = SectionCursor::new(other.index, other.raw_content.clone(), size);
-        result.word_index = other.word_index;
-        result.line_index = result
-            .lines
-            .iter()
-            .enumerate()
-            .filter(|(_, e)| {
-                e.word_indexes.first().copied().unwrap_or_default() <= other.word_index
-                    && other.word_index <= e.word_indexes.last().copied().unwrap_or_default()
-            })
-            .map(|(i, _)| i)
-            .next()
-            .unwrap_or_default();
+        result.chunk_size = other.chunk_size;
+        result.goto_word(other.word_index);
         result
     }
 
@@ -195,12 +403,76 @@ impl SectionCursor {
         self.word_index
     }
 
+    /// Moves to the word with the given index in the section.
+    pub fn goto_word(&mut self, word_index: usize) {
+        self.word_index = word_index;
+        self.line_index = self
+            .lines
+            .iter()
+            .position(|l| l.word_position(word_index).is_some())
+            .unwrap_or_default();
+    }
+
+    /// The words of the section, in order.
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.lines
+            .iter()
+            .flat_map(|l| l.words.iter().map(|w| &l.content[w.clone()]))
+    }
+
     pub fn current_line(&self) -> Option<&Line> {
         self.line(self.line_index)
     }
 
+    /// The words shown at once from the current one, see [`Line::chunk`].
     pub fn current_word(&self) -> Option<String> {
-        self.current_line()?.current_word(self.word_index)
+        let line = self.current_line()?;
+        let range = line.byte_range(line.chunk(self.word_index, self.chunk_size)?);
+        Some(line.content[range].to_string())
+    }
+
+    /// The words shown at once from the current one, one by one.
+    pub fn current_chunk(&self) -> Vec<&str> {
+        let Some(line) = self.current_line() else {
+            return vec![];
+        };
+        line.chunk(self.word_index, self.chunk_size)
+            .map_or(vec![], |chunk| {
+                line.words[chunk]
+                    .iter()
+                    .map(|w| &line.content[w.clone()])
+                    .collect()
+            })
+    }
+
+    /// Indexes of the current words in the section.
+    pub fn current_words(&self) -> Option<Range<usize>> {
+        let line = self.current_line()?;
+        let chunk = line.chunk(self.word_index, self.chunk_size)?;
+        Some(line.word_indexes[chunk.start]..line.word_indexes[chunk.end - 1] + 1)
+    }
+
+    /// Whether the current words end their line.
+    fn at_line_end(&self) -> bool {
+        self.current_line().is_some_and(|l| {
+            l.chunk(self.word_index, self.chunk_size)
+                .is_some_and(|c| c.end == l.words.len())
+        })
+    }
+
+    /// Whether the current words end their paragraph.
+    pub fn at_paragraph_end(&self) -> bool {
+        self.at_line_end() && self.current_line().is_some_and(|l| l.ends_paragraph)
+    }
+
+    /// Whether the current words end the section.
+    pub fn at_section_end(&self) -> bool {
+        self.at_line_end()
+            && self
+                .lines
+                .iter()
+                .skip(self.line_index + 1)
+                .all(|l| l.word_indexes.is_empty())
     }
 
     pub fn line(&self, index: usize) -> Option<&Line> {
@@ -210,8 +482,7 @@ impl SectionCursor {
     pub fn prev_word(&mut self) -> bool {
         let index = self
             .current_line()
-            .map(|l| l.prev_word(self.word_index))
-            .flatten();
+            .and_then(|l| l.prev_word(self.word_index, self.chunk_size));
 
         if let Some(index) = index {
             self.word_index = index;
@@ -224,8 +495,7 @@ impl SectionCursor {
     pub fn next_word(&mut self) -> bool {
         let index = self
             .current_line()
-            .map(|l| l.next_word(self.word_index))
-            .flatten();
+            .and_then(|l| l.next_word(self.word_index, self.chunk_size));
 
         if let Some(index) = index {
             self.word_index = index;
@@ -235,12 +505,47 @@ impl SectionCursor {
         }
     }
 
+    /// Moves to the start of the current sentence, or of the previous one when already
+    /// there.
+    pub fn prev_sentence(&mut self) -> bool {
+        let start = self.sentences.iter().rfind(|s| **s < self.word_index);
+        self.goto_start(start.copied())
+    }
+
+    pub fn next_sentence(&mut self) -> bool {
+        let start = self.sentences.iter().find(|s| **s > self.word_index);
+        self.goto_start(start.copied())
+    }
+
+    /// Moves to the start of the current paragraph, or of the previous one when already
+    /// there.
+    pub fn prev_paragraph(&mut self) -> bool {
+        let start = self.paragraphs.iter().rfind(|s| **s < self.word_index);
+        self.goto_start(start.copied())
+    }
+
+    pub fn next_paragraph(&mut self) -> bool {
+        let start = self.paragraphs.iter().find(|s| **s > self.word_index);
+        self.goto_start(start.copied())
+    }
+
+    fn goto_start(&mut self, start: Option<usize>) -> bool {
+        start.inspect(|s| self.goto_word(*s)).is_some()
+    }
+
     pub fn next_line(&mut self) -> bool {
         if self.line_index + 1 > self.lines.len() {
             return false;
         }
 
-        self.line_index += 1;
+        self.line_index = self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(self.line_index + 1)
+            .find(|(_, l)| !l.word_indexes.is_empty())
+            .map(|(i, _)| i)
+            .unwrap_or(self.lines.len());
 
         self.word_index = self
             .current_line()
@@ -250,10 +555,13 @@ impl SectionCursor {
     }
 
     pub fn prev_line(&mut self) -> bool {
-        if self.line_index == 0 {
+        let Some(line_index) = self.lines[..self.line_index.min(self.lines.len())]
+            .iter()
+            .rposition(|l| !l.word_indexes.is_empty())
+        else {
             return false;
-        }
-        self.line_index = self.line_index.saturating_sub(1);
+        };
+        self.line_index = line_index;
         self.word_index = self
             .current_line()
             .map(|l| l.last_word_index())
@@ -262,31 +570,178 @@ impl SectionCursor {
     }
 }
 
-fn lines(content: String) -> Vec<Line> {
+/// Renders sections like `html2text`'s plain decorator, additionally tagging
+/// preformatted text so that code blocks can be left out of the word stream.
+#[derive(Clone)]
+struct SectionDecorator(PlainDecorator);
+
+impl SectionDecorator {
+    fn new() -> Self {
+        Self(PlainDecorator::new())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+enum Annotation {
+    #[default]
+    Text,
+    Preformatted,
+}
+
+impl TextDecorator for SectionDecorator {
+    type Annotation = Annotation;
+
+    fn decorate_link_start(&mut self, url: &str) -> (String, Annotation) {
+        (self.0.decorate_link_start(url).0, Annotation::Text)
+    }
+    fn decorate_link_end(&mut self) -> String {
+        self.0.decorate_link_end()
+    }
+    fn decorate_em_start(&mut self) -> (String, Annotation) {
+        (self.0.decorate_em_start().0, Annotation::Text)
+    }
+    fn decorate_em_end(&mut self) -> String {
+        self.0.decorate_em_end()
+    }
+    fn decorate_strong_start(&mut self) -> (String, Annotation) {
+        (self.0.decorate_strong_start().0, Annotation::Text)
+    }
+    fn decorate_strong_end(&mut self) -> String {
+        self.0.decorate_strong_end()
+    }
+    fn decorate_strikeout_start(&mut self) -> (String, Annotation) {
+        (self.0.decorate_strikeout_start().0, Annotation::Text)
+    }
+    fn decorate_strikeout_end(&mut self) -> String {
+        self.0.decorate_strikeout_end()
+    }
+    fn decorate_code_start(&mut self) -> (String, Annotation) {
+        (self.0.decorate_code_start().0, Annotation::Text)
+    }
+    fn decorate_code_end(&mut self) -> String {
+        self.0.decorate_code_end()
+    }
+    fn decorate_preformat_first(&mut self) -> Annotation {
+        Annotation::Preformatted
+    }
+    fn decorate_preformat_cont(&mut self) -> Annotation {
+        Annotation::Preformatted
+    }
+    fn decorate_image(&mut self, src: &str, title: &str) -> (String, Annotation) {
+        (self.0.decorate_image(src, title).0, Annotation::Text)
+    }
+    fn header_prefix(&mut self, level: usize) -> String {
+        self.0.header_prefix(level)
+    }
+    fn quote_prefix(&mut self) -> String {
+        self.0.quote_prefix()
+    }
+    fn unordered_item_prefix(&mut self) -> String {
+        self.0.unordered_item_prefix()
+    }
+    fn ordered_item_prefix(&mut self, i: i64) -> String {
+        self.0.ordered_item_prefix(i)
+    }
+    fn make_subblock_decorator(&self) -> Self {
+        Self(self.0.make_subblock_decorator())
+    }
+    fn finalise(&mut self, links: Vec<String>) -> Vec<TaggedLine<Annotation>> {
+        self.0
+            .finalise(links)
+            .into_iter()
+            .map(|l| TaggedLine::from_string(l.into_string(), &Annotation::Text))
+            .collect()
+    }
+}
+
+fn is_preformatted(line: &TaggedLine<Vec<Annotation>>) -> bool {
+    let mut text = line
+        .tagged_strings()
+        .filter(|s| !s.s.trim().is_empty())
+        .peekable();
+    text.peek().is_some() && text.all(|s| s.tag.contains(&Annotation::Preformatted))
+}
+
+fn lines(rendered: &[TaggedLine<Vec<Annotation>>]) -> Vec<Line> {
+    let rendered: Vec<(String, bool)> = rendered
+        .iter()
+        .map(|l| (l.clone().into_string(), is_preformatted(l)))
+        .collect();
     let mut result = vec![];
     let mut global_words_index = 0;
-    for (i, l) in content.lines().filter(|l| !l.is_empty()).enumerate() {
-        let valid_words: Vec<usize> = l
-            .split_whitespace()
-            .enumerate()
-            //.filter(|(_, w)| w.chars().any(char::is_alphabetic))
-            .map(|(i, _)| global_words_index + i)
-            .collect();
-        global_words_index = valid_words.last().copied().unwrap_or_default();
+    for (position, (l, preformatted)) in rendered.iter().enumerate() {
+        if l.is_empty() {
+            continue;
+        }
+        let words = if *preformatted {
+            vec![]
+        } else {
+            word_ranges(l)
+        };
+        let valid_words: Vec<usize> =
+            (global_words_index..global_words_index + words.len()).collect();
+        if let Some(last) = valid_words.last() {
+            global_words_index = last + 1;
+        }
         result.push(Line {
-            index: i,
+            index: result.len(),
             word_indexes: valid_words,
-            content: l.to_string(),
+            words,
+            content: l.clone(),
+            ends_paragraph: rendered
+                .get(position + 1)
+                .is_none_or(|(next, _)| next.is_empty()),
         });
     }
     result
 }
 
+/// First words of the paragraphs and of the sentences of a section. Paragraphs are
+/// separated by blank lines, which don't depend on the width the section is rendered at,
+/// and sentences follow the UAX #29 sentence boundaries within a paragraph.
+fn boundaries(lines: &[Line]) -> (Vec<usize>, Vec<usize>) {
+    let mut paragraphs = vec![];
+    let mut sentences = vec![];
+    // Text of the current paragraph, with the offset of each of its words.
+    let mut text = String::new();
+    let mut starts: Vec<(usize, usize)> = vec![];
+    let mut flush = |text: &mut String, starts: &mut Vec<(usize, usize)>| {
+        for (offset, _) in text.split_sentence_bound_indices() {
+            let word = starts.partition_point(|(start, _)| *start < offset);
+            if let Some((_, index)) = starts.get(word) {
+                if sentences.last() != Some(index) {
+                    sentences.push(*index);
+                }
+            }
+        }
+        text.clear();
+        starts.clear();
+    };
+    for line in lines {
+        for (range, index) in line.words.iter().zip(&line.word_indexes) {
+            if starts.is_empty() {
+                paragraphs.push(*index);
+            }
+            starts.push((text.len(), *index));
+            text.push_str(&line.content[range.clone()]);
+            text.push(' ');
+        }
+        if line.ends_paragraph {
+            flush(&mut text, &mut starts);
+        }
+    }
+    flush(&mut text, &mut starts);
+    (paragraphs, sentences)
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Line {
     pub index: usize,
     pub word_indexes: Vec<usize>,
+    /// Byte ranges of the words in `content`, matching `word_indexes`.
+    pub words: Vec<Range<usize>>,
     pub content: String,
+    pub ends_paragraph: bool,
 }
 
 impl Line {
@@ -296,12 +751,25 @@ impl Line {
     pub fn last_word_index(&self) -> usize {
         self.word_indexes.last().copied().unwrap_or_default()
     }
-    pub fn current_word(&self, global_word_index: usize) -> Option<String> {
-        let index = self.word_position(global_word_index)?;
-        self.content
-            .split_whitespace()
-            .nth(index)
-            .map(|s| s.to_string())
+    /// Positions in the line of the words shown together with the given one: up to
+    /// `size` words, ending early after a word that ends with punctuation.
+    pub fn chunk(&self, global_word_index: usize, size: usize) -> Option<Range<usize>> {
+        let position = self.word_position(global_word_index)?;
+        let mut start = 0;
+        for (i, word) in self.words.iter().enumerate() {
+            let ends_phrase = !self.content[word.clone()].ends_with(char::is_alphanumeric);
+            if i + 1 - start >= size || ends_phrase || i + 1 == self.words.len() {
+                if position <= i {
+                    return Some(start..i + 1);
+                }
+                start = i + 1;
+            }
+        }
+        None
+    }
+    /// Byte range in the line covered by the words at the given positions.
+    pub fn byte_range(&self, positions: Range<usize>) -> Range<usize> {
+        self.words[positions.start].start..self.words[positions.end - 1].end
     }
     pub fn word_position(&self, global_word_index: usize) -> Option<usize> {
         self.word_indexes
@@ -310,51 +778,15 @@ impl Line {
             .map(|(i, _)| i)
     }
 
-    fn prev_word(&self, global_word_index: usize) -> Option<usize> {
-        let line_index = self.word_position(global_word_index)?;
-        if line_index > 0 {
-            self.word_indexes.get(line_index - 1).copied()
-        } else {
-            None
-        }
+    fn prev_word(&self, global_word_index: usize, chunk_size: usize) -> Option<usize> {
+        let chunk = self.chunk(global_word_index, chunk_size)?;
+        let previous = self.word_indexes.get(chunk.start.checked_sub(1)?)?;
+        let previous = self.chunk(*previous, chunk_size)?;
+        self.word_indexes.get(previous.start).copied()
     }
-    fn next_word(&self, global_word_index: usize) -> Option<usize> {
-        let line_index = self.word_position(global_word_index)?;
-        if line_index < self.word_indexes.len() - 1 {
-            self.word_indexes.get(line_index + 1).copied()
-        } else {
-            None
-        }
-    }
-}
-
-pub struct EpubDoc(epub::doc::EpubDoc<BufReader<File>>, Vec<TableOfContentNode>);
-
-impl Deref for EpubDoc {
-    type Target = epub::doc::EpubDoc<BufReader<File>>;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl DerefMut for EpubDoc {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
-
-impl EpubDoc {
-    pub fn open(path: &Path) -> Result<Self> {
-        let doc = epub::doc::EpubDoc::new(path)?;
-        let toc = doc
-            .toc
-            .iter()
-            .map(|t| TableOfContentNode::new(t, &doc))
-            .collect();
-        Ok(Self(doc, toc))
-    }
-    pub fn table_of_contents(&self) -> &[TableOfContentNode] {
-        &self.1
+    fn next_word(&self, global_word_index: usize, chunk_size: usize) -> Option<usize> {
+        let chunk = self.chunk(global_word_index, chunk_size)?;
+        self.word_indexes.get(chunk.end).copied()
     }
 }
 
@@ -363,6 +795,12 @@ pub struct DocState {
     pub identifier: String,
     pub section_index: usize,
     pub word_index: usize,
+    /// Last reading speed used for the document.
+    #[serde(default)]
+    pub wpm: Option<f64>,
+    /// Number of words in every section, to avoid counting them again.
+    #[serde(default)]
+    pub word_counts: Vec<usize>,
 }
 
 impl DocState {
@@ -371,11 +809,12 @@ impl DocState {
             identifier,
             section_index: 0,
             word_index: 0,
+            wpm: None,
+            word_counts: vec![],
         }
     }
-    pub fn load(config_dir: &Path, identifier: String) -> Self {
-        let config_path = config_dir.join(identifier.clone());
-        let state = std::fs::read_to_string(config_path).ok();
+    pub fn load(state_dir: &Path, identifier: String) -> Self {
+        let state = crate::state::read_document(state_dir, &identifier, None);
 
         if let Some(state) = state {
             serde_json::from_str(&state).unwrap_or_else(|_| Self::new(identifier))
@@ -383,13 +822,9 @@ impl DocState {
             Self::new(identifier)
         }
     }
-    pub fn store(&self, config_dir: &Path) -> anyhow::Result<()> {
-        let config_path = config_dir.join(self.identifier.clone());
-        let file = File::create(config_path)?;
-        let mut writer = BufWriter::new(file);
-        serde_json::to_writer(&mut writer, &self)?;
-        writer.flush()?;
-        Ok(())
+    pub fn store(&self, state_dir: &Path) -> anyhow::Result<()> {
+        let path = crate::state::document_path(state_dir, &self.identifier, None);
+        crate::state::store_json(&path, self)
     }
 }
 #[cfg(test)]
@@ -401,28 +836,185 @@ mod test {
 
     #[rstest]
     fn it_gets_a_section(epub: EpubDoc) {
-        let mut cursor = DocumentCursor::new(epub, DocState::new("xxxx".to_string()));
+        let doc_state = DocState {
+            section_index: 1,
+            ..DocState::new("xxxx".to_string())
+        };
+        let mut cursor = DocumentCursor::new(Box::new(epub), doc_state);
 
         let_assert!(section = cursor.current_section());
         check!(section.index == 1);
 
         cursor.next_section();
-        check!(cursor.doc.spine.len() > 1);
+        check!(cursor.sections() > 1);
         let_assert!(section = cursor.current_section());
         check!(section.index == 2);
     }
 
     #[rstest]
     fn it_gets_identifier(epub: EpubDoc) {
-        dbg!(&epub.unique_identifier);
-        check!(false);
+        check!(epub.identifier() == "c7af4885-7934-4428-81c5-f75ff6c954f2");
+    }
+
+    #[rstest]
+    fn it_gets_metadata(epub: EpubDoc) {
+        let_assert!(Some(title) = epub.metadata().title);
+        check!(title.starts_with("Think Like a Freak"));
+    }
+
+    #[rstest]
+    fn it_gets_the_toc_index() {
+        let doc = TextDoc::parse(
+            "Preface.\n\nPART I\n\nChapter 1\n\nOne.\n\nChapter 2\n\nTwo.\n\nPART II\n\nThree.\n",
+        );
+        let mut cursor = DocumentCursor::new(Box::new(doc), DocState::new("xxxx".to_string()));
+
+        check!(cursor.toc_index().is_empty());
+        cursor.goto_section(3);
+        check!(cursor.toc_index() == vec![0, 1]);
+        cursor.goto_section(4);
+        check!(cursor.toc_index() == vec![1]);
+        cursor.goto_toc_entry(&[0, 0]);
+        check!(cursor.section_index() == 2);
+    }
+
+    #[rstest]
+    fn it_skips_code_blocks() {
+        let doc =
+            MarkdownDoc::parse("# Build\n\nRun this:\n\n```\ncargo build\n```\n\nand wait.\n");
+        let mut cursor = DocumentCursor::new(Box::new(doc), DocState::new("xxxx".to_string()));
+        cursor.current_section_or_resize(40);
+
+        let section = cursor.current_section();
+        check!(section.content.contains("cargo build"));
+        let mut words = vec![];
+        while let Some(word) = section.current_word() {
+            words.push(word);
+            section.next_word();
+        }
+        check!(words == vec!["Build", "Run", "this:", "and", "wait."]);
+    }
+
+    #[rstest]
+    fn it_opens_by_extension() {
+        check!(open(Path::new("test.epub")).is_ok());
+        check!(open(Path::new("test.unknown")).is_err());
     }
 
     #[fixture]
     fn epub() -> EpubDoc {
-        let path = Path::new("Extreme ProgrammingExplained.epub");
+        let path = Path::new("test.epub");
         EpubDoc::open(path).unwrap()
     }
+    #[rstest]
+    fn it_steps_by_chunks() {
+        let doc = MarkdownDoc::parse("One two three four, five six.\n");
+        let mut cursor = DocumentCursor::new(Box::new(doc), DocState::new("xxxx".to_string()));
+        cursor.set_chunk_size(3);
+
+        let section = cursor.current_section_or_resize(80);
+        let mut chunks = vec![];
+        while let Some(chunk) = section.current_word() {
+            chunks.push(chunk);
+            section.next_word();
+        }
+        check!(chunks == vec!["One two three", "four,", "five six."]);
+        check!(section.current_chunk().is_empty());
+        check!(section.prev_line());
+        check!(section.current_word().as_deref() == Some("five six."));
+        check!(section.current_chunk() == vec!["five", "six."]);
+        check!(section.prev_word());
+        check!(section.current_word().as_deref() == Some("four,"));
+    }
+
+    #[rstest]
+    fn it_moves_by_sentences_and_paragraphs() {
+        let doc = MarkdownDoc::parse(
+            "One two. Three four! \"Five?\" Six, e.g. seven.\n\nEight nine\nten.\n",
+        );
+        let mut cursor = DocumentCursor::new(Box::new(doc), DocState::new("xxxx".to_string()));
+        // Narrow enough for the first paragraph to wrap.
+        let section = cursor.current_section_or_resize(12);
+
+        let mut sentences = vec![];
+        while let Some(word) = section.current_word() {
+            sentences.push(word);
+            if !section.next_sentence() {
+                break;
+            }
+        }
+        check!(sentences == vec!["One", "Three", "\"Five?\"", "Six,", "Eight"]);
+        section.goto_word(6);
+        check!(section.prev_sentence());
+        check!(section.current_word().as_deref() == Some("Six,"));
+        check!(section.prev_paragraph());
+        check!(section.current_word().as_deref() == Some("One"));
+        check!(!section.prev_paragraph());
+        check!(section.next_paragraph());
+        check!(section.current_word().as_deref() == Some("Eight"));
+        check!(!section.next_paragraph());
+    }
+
+    #[rstest]
+    fn it_resumes_at_the_saved_word() {
+        let doc = MarkdownDoc::parse("One two three.\n\nFour five.\n");
+        let doc_state = DocState {
+            word_index: 3,
+            ..DocState::new("xxxx".to_string())
+        };
+        let mut cursor = DocumentCursor::new(Box::new(doc), doc_state);
+
+        check!(cursor.current_section().current_chunk() == vec!["Four"]);
+    }
+
+    #[rstest]
+    fn it_estimates_the_words_left() {
+        let doc = MarkdownDoc::parse(
+            "# One\n\nTwo three.\n\n# Four\n\nFive.\n\n# Six\n\nSeven eight nine.\n",
+        );
+        let mut cursor = DocumentCursor::new(Box::new(doc), DocState::new("xxxx".to_string()));
+
+        cursor.current_section().next_word();
+        check!(cursor.words_left() == 2 + 2 + 4);
+        check!(cursor.progress() == 1.0 / 9.0);
+        check!(cursor.doc_state().word_counts == vec![3, 2, 4]);
+    }
+
+    #[rstest]
+    fn it_reuses_the_saved_word_counts() {
+        let doc = MarkdownDoc::parse(
+            "# One\n\nTwo three.\n\n# Four\n\nFive.\n\n# Six\n\nSeven eight nine.\n",
+        );
+        let doc_state = DocState {
+            word_counts: vec![10, 10, 10],
+            ..DocState::new("xxxx".to_string())
+        };
+        let cursor = DocumentCursor::new(Box::new(doc), doc_state);
+
+        // Only the section being read is counted again.
+        check!(cursor.words_left() == 3 + 10 + 10);
+    }
+
+    #[rstest]
+    fn it_searches_every_section() {
+        let doc = MarkdownDoc::parse(
+            "# One\n\nThe quick brown fox.\n\n# Two\n\nA lazy dog.\n\nAnother QUICK\nbrown bear.\n",
+        );
+        let mut cursor = DocumentCursor::new(Box::new(doc), DocState::new("xxxx".to_string()));
+
+        let hits = cursor.search("quick  brown");
+        check!(hits.len() == 2);
+        check!(hits[0].section_index == 0);
+        check!(hits[0].word_index == 2);
+        check!(hits[1].section_index == 1);
+        check!(hits[1].word_index == 5);
+        check!(hits[1].chapter.as_deref() == Some("Two"));
+        check!(hits[1].snippet == "Two A lazy dog. Another QUICK brown bear.");
+
+        check!(cursor.goto_word(hits[1].section_index, hits[1].word_index));
+        check!(cursor.current_section().current_word().as_deref() == Some("QUICK"));
+    }
+
     #[fixture]
     fn content() -> &'static str {
         "[Dedication][1]\n\nFor ELLEN,\nwho has been there for everything,\nincluding the books.\n\n—SJD\n\nFor my sister LINDA LEVITT JINES,\nwhose creative genius amazed,\namused, and inspired me.\n\n—SDL\n\n[1]: part0002.html#ded\n"
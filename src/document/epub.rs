@@ -0,0 +1,65 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::Result;
+use epub::doc::NavPoint;
+
+use super::{Document, Metadata, TableOfContentNode};
+
+pub struct EpubDoc {
+    doc: epub::doc::EpubDoc<BufReader<File>>,
+    toc: Vec<TableOfContentNode>,
+    fallback_identifier: String,
+}
+
+impl EpubDoc {
+    pub fn open(path: &Path) -> Result<Self> {
+        let doc = epub::doc::EpubDoc::new(path)?;
+        let toc = doc.toc.iter().map(|t| toc_node(t, &doc)).collect();
+        let fallback_identifier = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(Self {
+            doc,
+            toc,
+            fallback_identifier,
+        })
+    }
+}
+
+impl Document for EpubDoc {
+    fn sections(&self) -> usize {
+        self.doc.get_num_pages()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        let id = self.doc.spine.get(index)?.clone();
+        self.doc.get_resource(&id).map(|(content, _)| content)
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.doc
+            .unique_identifier
+            .clone()
+            .unwrap_or_else(|| self.fallback_identifier.clone())
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.doc.mdata("title"),
+            author: self.doc.mdata("creator"),
+        }
+    }
+}
+
+fn toc_node(value: &NavPoint, doc: &epub::doc::EpubDoc<BufReader<File>>) -> TableOfContentNode {
+    TableOfContentNode {
+        index: doc.resource_uri_to_chapter(&value.content).unwrap(),
+        name: value.label.clone(),
+        children: value.children.iter().map(|t| toc_node(t, doc)).collect(),
+    }
+}
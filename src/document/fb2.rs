@@ -0,0 +1,328 @@
+use std::{fs::File, io::Read, path::Path};
+
+use anyhow::{anyhow, Result};
+use encoding_rs::{Encoding, UTF_8};
+use regex::Regex;
+use roxmltree::Node;
+
+use super::{
+    content_identifier, escape_html, toc_from_headings, Document, Metadata, TableOfContentNode,
+};
+
+/// FictionBook document, either plain or zipped. Every `<section>` becomes a section of
+/// its own, containing everything up to its first nested section.
+pub struct Fb2Doc {
+    sections: Vec<String>,
+    toc: Vec<TableOfContentNode>,
+    identifier: String,
+    title: Option<String>,
+    author: Option<String>,
+}
+
+impl Fb2Doc {
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = if is_zipped(path) {
+            let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+            let name = archive
+                .file_names()
+                .find(|n| n.to_lowercase().ends_with(".fb2"))
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("no .fb2 file found in {}", path.display()))?;
+            let mut content = vec![];
+            archive.by_name(&name)?.read_to_end(&mut content)?;
+            content
+        } else {
+            std::fs::read(path)?
+        };
+        Self::parse(&decode(&content))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let xml = roxmltree::Document::parse(content)?;
+        let root = xml.root_element();
+        let description = child(root, "description");
+        let identifier = description
+            .and_then(|d| child(d, "document-info"))
+            .and_then(|d| child(d, "id"))
+            .map(text)
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| content_identifier(content.as_bytes()));
+        let title = description
+            .and_then(|d| child(d, "title-info"))
+            .and_then(|d| child(d, "book-title"))
+            .map(text);
+        let author = description
+            .and_then(|d| child(d, "title-info"))
+            .and_then(|d| child(d, "author"))
+            .map(|a| {
+                ["first-name", "middle-name", "last-name", "nickname"]
+                    .iter()
+                    .filter_map(|name| child(a, name).map(text))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|a| !a.is_empty());
+
+        let mut sections = vec![];
+        let mut toc = vec![];
+        for (i, body) in children(root, "body").enumerate() {
+            // The first body is the book itself, the following ones hold notes and comments.
+            let level = if i == 0 {
+                1
+            } else {
+                let name = child(body, "title")
+                    .map(title_text)
+                    .or_else(|| body.attribute("name").map(str::to_string))
+                    .unwrap_or("Notes".to_string());
+                toc.push((1, sections.len(), name));
+                2
+            };
+            let html = content_html(body);
+            if !html.is_empty() {
+                sections.push(html);
+            }
+            for section in children(body, "section") {
+                add_section(section, level, &mut sections, &mut toc);
+            }
+        }
+
+        Ok(Self {
+            sections,
+            toc: toc_from_headings(&toc),
+            identifier,
+            title,
+            author,
+        })
+    }
+}
+
+impl Document for Fb2Doc {
+    fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        self.sections.get(index).map(|s| s.as_bytes().to_vec())
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            author: self.author.clone(),
+        }
+    }
+}
+
+pub fn is_zipped(path: &Path) -> bool {
+    path.to_string_lossy().to_lowercase().ends_with(".fb2.zip")
+}
+
+/// Decodes the document using the encoding of its XML declaration, as FictionBooks are
+/// often not UTF-8.
+fn decode(content: &[u8]) -> String {
+    let declaration = String::from_utf8_lossy(&content[..content.len().min(200)]).to_string();
+    let encoding = Regex::new(r#"^(?:\u{feff})?\s*<\?xml[^>]*encoding\s*=\s*["']([^"']+)["']"#)
+        .unwrap()
+        .captures(&declaration)
+        .and_then(|c| Encoding::for_label(c[1].as_bytes()))
+        .unwrap_or(UTF_8);
+    encoding.decode(content).0.into_owned()
+}
+
+fn add_section(
+    node: Node,
+    level: usize,
+    sections: &mut Vec<String>,
+    toc: &mut Vec<(usize, usize, String)>,
+) {
+    let html = content_html(node);
+    if !html.is_empty() {
+        if let Some(title) = child(node, "title") {
+            toc.push((level, sections.len(), title_text(title)));
+        }
+        sections.push(html);
+    }
+    for section in children(node, "section") {
+        add_section(section, level + 1, sections, toc);
+    }
+}
+
+/// HTML for the content of a body or section, leaving out nested sections.
+fn content_html(node: Node) -> String {
+    let mut html = String::new();
+    for child in node
+        .children()
+        .filter(|c| c.is_element() && c.tag_name().name() != "section")
+    {
+        to_html(child, &mut html);
+    }
+    html
+}
+
+fn to_html(node: Node, html: &mut String) {
+    if node.is_text() {
+        html.push_str(&escape_html(node.text().unwrap_or_default()));
+        return;
+    }
+    if !node.is_element() {
+        return;
+    }
+    let tag = match node.tag_name().name() {
+        "title" => {
+            html.push_str(&format!("<h2>{}</h2>", escape_html(&title_text(node))));
+            return;
+        }
+        "empty-line" => {
+            html.push_str("<br>");
+            return;
+        }
+        "image" | "binary" => return,
+        "p" | "v" | "text-author" => "p",
+        "subtitle" => "h3",
+        "emphasis" => "em",
+        "strong" => "strong",
+        "strikethrough" => "del",
+        "code" => "code",
+        "sub" => "sub",
+        "sup" => "sup",
+        "epigraph" | "cite" | "annotation" => "blockquote",
+        _ => "div",
+    };
+    html.push_str(&format!("<{tag}>"));
+    for child in node.children() {
+        to_html(child, html);
+    }
+    html.push_str(&format!("</{tag}>"));
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|c| c.is_element() && c.tag_name().name() == name)
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |c| c.is_element() && c.tag_name().name() == name)
+}
+
+fn text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Titles are made of paragraphs, usually a number and a name.
+fn title_text(node: Node) -> String {
+    let paragraphs: Vec<String> = children(node, "p").map(text).collect();
+    if paragraphs.is_empty() {
+        text(node)
+    } else {
+        paragraphs.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+    use std::io::Write;
+
+    #[rstest]
+    fn it_reads_identifier_and_title(book: &str) {
+        let doc = Fb2Doc::parse(book).unwrap();
+
+        check!(doc.identifier() == "2b1a5e0c-fb2-test");
+        check!(doc.metadata().title.as_deref() == Some("Тестовая книга"));
+        check!(doc.metadata().author.as_deref() == Some("Лев Толстой"));
+    }
+
+    #[rstest]
+    fn it_maps_sections(book: &str) {
+        let mut doc = Fb2Doc::parse(book).unwrap();
+
+        check!(doc.sections() == 5);
+        let_assert!(Some(chapter) = doc.section(2));
+        let chapter = String::from_utf8(chapter).unwrap();
+        check!(chapter.contains("<h2>Глава 1 Начало</h2>"));
+        check!(chapter.contains("<em>Текст</em>"));
+
+        let toc = doc.table_of_contents();
+        check!(toc.len() == 2);
+        check!(toc[0].name == "Часть первая");
+        check!(toc[0].index == 1);
+        check!(toc[0].children.len() == 2);
+        check!(toc[0].children[1].index == 3);
+        check!(toc[1].name == "notes");
+        check!(toc[1].index == 4);
+    }
+
+    #[rstest]
+    fn it_decodes_the_declared_encoding(book: &str) {
+        let book = book.replace("encoding=\"utf-8\"", "encoding=\"windows-1251\"");
+        let (encoded, _, _) = encoding_rs::WINDOWS_1251.encode(&book);
+
+        let doc = Fb2Doc::parse(&decode(&encoded)).unwrap();
+        check!(doc.metadata().title.as_deref() == Some("Тестовая книга"));
+    }
+
+    #[rstest]
+    fn it_opens_zipped_books(book: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("book.fb2.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("book.fb2", Default::default()).unwrap();
+        zip.write_all(book.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let doc = Fb2Doc::open(&path).unwrap();
+        check!(doc.sections() == 5);
+    }
+
+    #[fixture]
+    fn book() -> &'static str {
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0">
+  <description>
+    <title-info>
+      <author><first-name>Лев</first-name><last-name>Толстой</last-name></author>
+      <book-title>Тестовая книга</book-title>
+    </title-info>
+    <document-info><id> 2b1a5e0c-fb2-test </id></document-info>
+  </description>
+  <body>
+    <title><p>Тестовая книга</p></title>
+    <section>
+      <title><p>Часть первая</p></title>
+      <epigraph><p>Эпиграф</p></epigraph>
+      <section>
+        <title><p>Глава 1</p><p>Начало</p></title>
+        <p><emphasis>Текст</emphasis> первой главы.</p>
+      </section>
+      <section>
+        <title><p>Глава 2</p></title>
+        <p>Текст второй главы.</p><empty-line/><image/>
+      </section>
+    </section>
+  </body>
+  <body name="notes">
+    <section><title><p>1</p></title><p>Примечание.</p></section>
+  </body>
+</FictionBook>"#
+    }
+}
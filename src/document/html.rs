@@ -0,0 +1,303 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use html2text::render::text_renderer::TrivialDecorator;
+use regex::Regex;
+
+use super::{content_identifier, toc_from_headings, Document, Metadata, TableOfContentNode};
+
+/// A single HTML page, split on its top-level headings, or a directory of HTML pages
+/// with one section per page.
+pub struct HtmlDoc {
+    sections: Vec<String>,
+    toc: Vec<TableOfContentNode>,
+    identifier: String,
+    title: Option<String>,
+}
+
+impl HtmlDoc {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut doc = if path.is_dir() {
+            Self::open_dir(path)?
+        } else {
+            Self::parse(&String::from_utf8_lossy(&std::fs::read(path)?))
+        };
+        if doc.title.is_none() {
+            doc.title = path.file_stem().map(|s| s.to_string_lossy().to_string());
+        }
+        Ok(doc)
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let identifier = content_identifier(content.as_bytes());
+        let title = title(content);
+        let content = &body(content);
+        let headings = headings(content);
+        let top_level = headings.iter().map(|h| h.0).min();
+        let mut starts: Vec<usize> = headings
+            .iter()
+            .filter(|h| Some(h.0) == top_level)
+            .map(|h| h.1)
+            .collect();
+        if starts.first().is_none_or(|s| !is_blank(&content[..*s])) {
+            starts.insert(0, 0);
+        }
+        let sections: Vec<String> = starts
+            .iter()
+            .enumerate()
+            .map(|(i, start)| {
+                let end = starts.get(i + 1).copied().unwrap_or(content.len());
+                content[*start..end].to_string()
+            })
+            .collect();
+        let toc: Vec<(usize, usize, String)> = headings
+            .into_iter()
+            .map(|(level, start, name)| (level, starts.partition_point(|s| *s <= start) - 1, name))
+            .collect();
+
+        Self {
+            sections,
+            toc: toc_from_headings(&toc),
+            identifier,
+            title,
+        }
+    }
+
+    fn open_dir(path: &Path) -> Result<Self> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && is_html(p))
+            .collect();
+        if files.is_empty() {
+            bail!("no HTML files found in {}", path.display());
+        }
+        files.sort();
+
+        let index = files
+            .iter()
+            .position(|f| f.file_stem().is_some_and(|s| s == "index"));
+        let mut title = None;
+        if let Some(index) = index {
+            let index = files.remove(index);
+            let content = std::fs::read_to_string(&index)?;
+            title = self::title(&content);
+            let mut linked = vec![index];
+            for link in links(&content) {
+                if let Some(position) = files
+                    .iter()
+                    .position(|f| f.file_name() == Some(link.as_ref()))
+                {
+                    linked.push(files.remove(position));
+                }
+            }
+            linked.append(&mut files);
+            files = linked;
+        }
+
+        let mut sections = vec![];
+        let mut toc = vec![];
+        for (i, file) in files.iter().enumerate() {
+            let content = String::from_utf8_lossy(&std::fs::read(file)?).to_string();
+            let headings = headings(&body(&content));
+            if let Some(top_level) = headings.iter().map(|h| h.0).min() {
+                toc.extend(
+                    headings
+                        .into_iter()
+                        .map(|(level, _, name)| (level - top_level + 1, i, name)),
+                );
+            } else {
+                let name = self::title(&content).unwrap_or_else(|| {
+                    file.file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                });
+                toc.push((1, i, name));
+            }
+            sections.push(content);
+        }
+
+        Ok(Self {
+            identifier: content_identifier(sections.concat().as_bytes()),
+            sections,
+            toc: toc_from_headings(&toc),
+            title,
+        })
+    }
+}
+
+impl Document for HtmlDoc {
+    fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        self.sections.get(index).map(|s| s.as_bytes().to_vec())
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            author: None,
+        }
+    }
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| matches!(e.to_lowercase().as_str(), "html" | "htm" | "xhtml"))
+}
+
+/// The content of the page's body, without the comments, scripts and styles, where
+/// headings would be found but not shown.
+fn body(content: &str) -> String {
+    let hidden = Regex::new(concat!(
+        r"(?is)<!--.*?(?:-->|\z)|<head\b.*?</head\s*>",
+        r"|<script\b.*?</script\s*>|<style\b.*?</style\s*>",
+    ))
+    .unwrap();
+    let wrapper = Regex::new(r"(?is)<!doctype[^>]*>|</?html\b[^>]*>|</?body\b[^>]*>").unwrap();
+    wrapper
+        .replace_all(&hidden.replace_all(content, ""), "")
+        .into_owned()
+}
+
+/// `<h1>` to `<h3>` headings as `(level, byte offset, text)`.
+fn headings(content: &str) -> Vec<(usize, usize, String)> {
+    let heading = Regex::new(r"(?is)<h([1-3])(?:\s[^>]*)?>(.*?)</h[1-3]\s*>").unwrap();
+    heading
+        .captures_iter(content)
+        .map(|c| {
+            let level = c[1].parse().unwrap();
+            (level, c.get(0).unwrap().start(), to_text(&c[2]))
+        })
+        .filter(|(_, _, name)| !name.is_empty())
+        .collect()
+}
+
+fn title(content: &str) -> Option<String> {
+    Regex::new(r"(?is)<title[^>]*>(.*?)</title\s*>")
+        .unwrap()
+        .captures(content)
+        .map(|c| to_text(&c[1]))
+        .filter(|t| !t.is_empty())
+}
+
+/// Targets of the relative links of a page, without fragments or queries.
+fn links(content: &str) -> Vec<String> {
+    Regex::new(r#"(?i)href\s*=\s*["']([^"'#?:]+)"#)
+        .unwrap()
+        .captures_iter(content)
+        .map(|c| c[1].rsplit('/').next().unwrap_or_default().to_string())
+        .collect()
+}
+
+fn to_text(html: &str) -> String {
+    html2text::from_read_with_decorator(html.as_bytes(), 1000, TrivialDecorator::new())
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_blank(html: &str) -> bool {
+    to_text(html).is_empty()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_splits_a_page_on_top_level_headings(article: &str) {
+        let mut doc = HtmlDoc::parse(article);
+
+        check!(doc.sections() == 3);
+        check!(doc.metadata().title.as_deref() == Some("Saved & read"));
+        let_assert!(Some(first) = doc.section(0));
+        check!(String::from_utf8(first).unwrap().contains("Intro"));
+    }
+
+    #[rstest]
+    fn it_ignores_headings_that_are_not_shown() {
+        let mut doc = HtmlDoc::parse(
+            "<html><head><title>Notes</title><style>h1 { color: red }</style></head><body>\
+             <!-- <h1>Draft</h1> --><p>Intro</p>\
+             <script>const title = \"<h1>Fake</h1>\";</script>\
+             <h1>Real</h1><p>One</p>\
+             </body></html>",
+        );
+
+        check!(doc.sections() == 2);
+        check!(doc.metadata().title.as_deref() == Some("Notes"));
+        let names: Vec<_> = doc
+            .table_of_contents()
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        check!(names == vec!["Real"]);
+        let_assert!(Some(first) = doc.section(0));
+        check!(String::from_utf8(first).unwrap() == "<p>Intro</p>");
+        let_assert!(Some(second) = doc.section(1));
+        check!(String::from_utf8(second).unwrap() == "<h1>Real</h1><p>One</p>");
+    }
+
+    #[rstest]
+    fn it_builds_the_toc_from_headings(article: &str) {
+        let doc = HtmlDoc::parse(article);
+
+        let toc = doc.table_of_contents();
+        check!(toc.len() == 2);
+        check!(toc[0].name == "First part");
+        check!(toc[0].index == 1);
+        check!(toc[0].children[0].name == "Details");
+        check!(toc[0].children[0].index == 1);
+        check!(toc[1].name == "Second part");
+        check!(toc[1].index == 2);
+    }
+
+    #[rstest]
+    fn it_orders_a_directory_by_its_index_page() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("index.html"),
+            "<title>Manual</title><a href=\"b.html\">B</a><a href=\"a.html#top\">A</a>",
+        )
+        .unwrap();
+        std::fs::write(dir.join("a.html"), "<h1>Alpha</h1><p>a</p>").unwrap();
+        std::fs::write(dir.join("b.html"), "<h2>Beta</h2><h3>Beta two</h3>").unwrap();
+        std::fs::write(dir.join("c.htm"), "<p>no headings</p>").unwrap();
+
+        let doc = HtmlDoc::open(dir).unwrap();
+
+        check!(doc.sections() == 4);
+        check!(doc.metadata().title.as_deref() == Some("Manual"));
+        let names: Vec<_> = doc
+            .table_of_contents()
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        check!(names == vec!["Manual", "Beta", "Alpha", "c"]);
+        check!(doc.table_of_contents()[1].children[0].name == "Beta two");
+    }
+
+    #[fixture]
+    fn article() -> &'static str {
+        "<html><head><title>Saved &amp; read</title></head><body>\
+         <p>Intro</p>\
+         <h1 class=\"x\">First part</h1><p>One</p><h2>Details</h2><p>More</p>\
+         <h1>Second <em>part</em></h1><p>Two</p>\
+         </body></html>"
+    }
+}
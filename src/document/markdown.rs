@@ -0,0 +1,156 @@
+use std::path::Path;
+
+use anyhow::Result;
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+
+use super::{content_identifier, toc_from_headings, Document, Metadata, TableOfContentNode};
+
+/// Deepest heading level shown in the table of contents.
+const MAX_TOC_LEVEL: usize = 3;
+
+/// Markdown document, split into one section per top-level heading.
+pub struct MarkdownDoc {
+    sections: Vec<String>,
+    toc: Vec<TableOfContentNode>,
+    identifier: String,
+    title: Option<String>,
+}
+
+impl MarkdownDoc {
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let mut doc = Self::parse(&content);
+        if doc.title.is_none() {
+            doc.title = path.file_stem().map(|s| s.to_string_lossy().to_string());
+        }
+        Ok(doc)
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_FOOTNOTES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS;
+        let events: Vec<Event> = Parser::new_ext(content, options).collect();
+        let top_level = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Start(Tag::Heading(level, _, _)) => Some(*level as usize),
+                _ => None,
+            })
+            .min();
+
+        let mut sections: Vec<Vec<Event>> = vec![vec![]];
+        let mut toc = vec![];
+        let mut heading: Option<(usize, String)> = None;
+        for event in events {
+            match &event {
+                Event::Start(Tag::Heading(level, _, _)) => {
+                    let level = *level as usize;
+                    if Some(level) == top_level && !sections.last().unwrap().is_empty() {
+                        sections.push(vec![]);
+                    }
+                    heading = Some((level, String::new()));
+                }
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, name)) = heading.as_mut() {
+                        name.push_str(text);
+                    }
+                }
+                Event::End(Tag::Heading(_, _, _)) => {
+                    if let Some((level, name)) = heading.take() {
+                        if level <= MAX_TOC_LEVEL {
+                            toc.push((level, sections.len() - 1, name));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            sections.last_mut().unwrap().push(event);
+        }
+
+        let title = toc
+            .iter()
+            .find(|(level, _, _)| *level == 1)
+            .map(|(_, _, name)| name.clone());
+        Self {
+            sections: sections
+                .into_iter()
+                .map(|events| {
+                    let mut section = String::new();
+                    html::push_html(&mut section, events.into_iter());
+                    section
+                })
+                .collect(),
+            toc: toc_from_headings(&toc),
+            identifier: content_identifier(content.as_bytes()),
+            title,
+        }
+    }
+}
+
+impl Document for MarkdownDoc {
+    fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        self.sections.get(index).map(|s| s.as_bytes().to_vec())
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            author: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_splits_on_top_level_headings(runbook: &str) {
+        let doc = MarkdownDoc::parse(runbook);
+
+        check!(doc.sections() == 3);
+        check!(doc.metadata().title.as_deref() == Some("Deploy"));
+    }
+
+    #[rstest]
+    fn it_nests_headings(runbook: &str) {
+        let doc = MarkdownDoc::parse(runbook);
+
+        let toc = doc.table_of_contents();
+        check!(toc.len() == 2);
+        check!(toc[0].name == "Deploy");
+        check!(toc[0].index == 1);
+        check!(toc[0].children.len() == 2);
+        check!(toc[0].children[0].name == "Build the image");
+        check!(toc[0].children[0].index == 1);
+        check!(toc[0].children[0].children[0].name == "Using cargo");
+        check!(toc[1].name == "Rollback");
+        check!(toc[1].index == 2);
+    }
+
+    #[fixture]
+    fn runbook() -> &'static str {
+        "Owned by the platform team.\n\n\
+         # Deploy\n\nShip it carefully.\n\n\
+         ## Build the image\n\n### Using `cargo`\n\n```sh\ncargo build --release\n```\n\n\
+         ## Release\n\nTag the commit.\n\n\
+         ##### Too deep\n\n\
+         # Rollback\n\nRevert the tag.\n"
+    }
+}
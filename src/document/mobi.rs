@@ -0,0 +1,594 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use encoding_rs::{UTF_8, WINDOWS_1252};
+use regex::bytes::Regex;
+
+use super::{content_identifier, toc_from_headings, Document, Metadata, TableOfContentNode};
+
+const NO_INDEX: u32 = u32::MAX;
+
+/// Unencrypted Kindle MOBI or AZW3 (KF8) book. The text records are decompressed into
+/// HTML and split into sections on page breaks (MOBI) or on skeleton files (KF8).
+pub struct MobiDoc {
+    sections: Vec<String>,
+    toc: Vec<TableOfContentNode>,
+    identifier: String,
+    title: Option<String>,
+    author: Option<String>,
+}
+
+impl MobiDoc {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let records = records(data)?;
+        let exth = records.first().map(|r| exth(r)).unwrap_or_default();
+        // Combination files carry a KF8 version of the book after the MOBI one.
+        let base = exth
+            .get(&121)
+            .and_then(|v| be32(v, 0))
+            .map(|b| b as usize)
+            .filter(|b| *b > 0 && *b < records.len())
+            .unwrap_or(0);
+        let header = Header::parse(
+            records
+                .get(base)
+                .ok_or_else(|| anyhow!("empty MOBI file"))?,
+        )?;
+
+        let mut text = vec![];
+        for record in records.iter().skip(base + 1).take(header.record_count) {
+            let size = record.len() - trailing_entries_size(record, header.extra_flags);
+            text.extend(decompress(&record[..size], header.compression));
+        }
+        text.truncate(header.text_length);
+
+        let index = |i: u32| (i != NO_INDEX).then(|| base + i as usize);
+        let ncx = index(header.ncx_index).and_then(|i| read_index(&records, i));
+        let (starts, toc) = if header.version >= 8 {
+            if let Some(end) = index(header.fdst_index)
+                .and_then(|i| records.get(i))
+                .and_then(|r| be32(r, 16))
+            {
+                text.truncate(end as usize);
+            }
+            let starts = index(header.skeleton_index)
+                .and_then(|i| read_index(&records, i))
+                .map(|(skeletons, _)| {
+                    skeletons
+                        .iter()
+                        .filter_map(|s| s.tags.get(&6)?.first().copied())
+                        .map(|s| s as usize)
+                        .collect()
+                })
+                .unwrap_or_else(|| split_offsets(&text, r"(?i)(<\?xml[^>]*>\s*)?<html[\s>]"));
+            let fragments = index(header.fragment_index)
+                .and_then(|i| read_index(&records, i))
+                .map(|(fragments, _)| fragments)
+                .unwrap_or_default();
+            let toc = ncx
+                .map(|ncx| {
+                    ncx_headings(&ncx, header.encoding_is_utf8, |entry| {
+                        let fragment = fragments.get(*entry.tags.get(&6)?.first()? as usize)?;
+                        fragment.tags.get(&3)?.first().map(|f| *f as usize)
+                    })
+                })
+                .unwrap_or_default();
+            (starts, toc)
+        } else {
+            let starts = split_offsets(&text, r"(?i)<mbp:pagebreak[^>]*>");
+            let toc = ncx
+                .map(|ncx| {
+                    ncx_headings(&ncx, header.encoding_is_utf8, |entry| {
+                        let offset = *entry.tags.get(&1)?.first()? as usize;
+                        Some(starts.partition_point(|s| *s <= offset).saturating_sub(1))
+                    })
+                })
+                .unwrap_or_default();
+            (starts, toc)
+        };
+
+        let sections = split_sections(&text, &starts, header.encoding_is_utf8);
+        let identifier = exth
+            .get(&113)
+            .or_else(|| exth.get(&104))
+            .map(|v| decode(v, header.encoding_is_utf8))
+            .unwrap_or_else(|| content_identifier(data));
+        let author = exth.get(&100).map(|v| decode(v, header.encoding_is_utf8));
+        let title = exth
+            .get(&503)
+            .map(|v| decode(v, header.encoding_is_utf8))
+            .or(header.full_name.map(|n| decode(n, header.encoding_is_utf8)));
+
+        Ok(Self {
+            sections,
+            toc: toc_from_headings(&toc),
+            identifier,
+            title,
+            author,
+        })
+    }
+}
+
+impl Document for MobiDoc {
+    fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        self.sections.get(index).map(|s| s.as_bytes().to_vec())
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            author: self.author.clone(),
+        }
+    }
+}
+
+/// The PalmDOC and MOBI headers found in the first record of a book.
+struct Header<'a> {
+    compression: u16,
+    text_length: usize,
+    record_count: usize,
+    version: u32,
+    encoding_is_utf8: bool,
+    full_name: Option<&'a [u8]>,
+    extra_flags: u16,
+    fdst_index: u32,
+    ncx_index: u32,
+    fragment_index: u32,
+    skeleton_index: u32,
+}
+
+impl<'a> Header<'a> {
+    fn parse(record: &'a [u8]) -> Result<Self> {
+        let truncated = || anyhow!("truncated MOBI header");
+        let compression = be16(record, 0).ok_or_else(truncated)?;
+        match compression {
+            1 | 2 => {}
+            17480 => bail!("HUFF/CDIC compressed MOBI books are not supported"),
+            c => bail!("unknown MOBI compression type {c}"),
+        }
+        if be16(record, 12).ok_or_else(truncated)? != 0 {
+            bail!("the book is DRM protected and cannot be opened");
+        }
+        let is_mobi = record.get(16..20) == Some(b"MOBI");
+        let header_length = if is_mobi {
+            be32(record, 20).unwrap_or(0)
+        } else {
+            0
+        };
+        let field = |offset: usize| {
+            if is_mobi && offset + 4 <= 16 + header_length as usize {
+                be32(record, offset)
+            } else {
+                None
+            }
+        };
+        let full_name = field(0x54).zip(field(0x58)).and_then(|(offset, length)| {
+            record.get(offset as usize..offset.checked_add(length)? as usize)
+        });
+        Ok(Self {
+            compression,
+            text_length: be32(record, 4).ok_or_else(truncated)? as usize,
+            record_count: be16(record, 8).ok_or_else(truncated)? as usize,
+            version: field(0x24).unwrap_or(0),
+            encoding_is_utf8: field(0x1C) == Some(65001),
+            full_name,
+            extra_flags: if header_length >= 0xE4 {
+                be16(record, 0xF2).unwrap_or(0)
+            } else {
+                0
+            },
+            fdst_index: field(0xC0).unwrap_or(NO_INDEX),
+            ncx_index: field(0xF4).unwrap_or(NO_INDEX),
+            fragment_index: field(0xF8).unwrap_or(NO_INDEX),
+            skeleton_index: field(0xFC).unwrap_or(NO_INDEX),
+        })
+    }
+}
+
+/// Splits a Palm database into its records.
+fn records(data: &[u8]) -> Result<Vec<&[u8]>> {
+    let kind = data.get(60..68).ok_or_else(|| anyhow!("not a MOBI file"))?;
+    if kind != b"BOOKMOBI" && kind != b"TEXtREAd" {
+        bail!("not a MOBI file");
+    }
+    let count = be16(data, 76).unwrap_or(0) as usize;
+    let offsets: Vec<usize> = (0..count)
+        .map(|i| be32(data, 78 + i * 8).map(|o| o as usize))
+        .collect::<Option<_>>()
+        .ok_or_else(|| anyhow!("truncated MOBI record list"))?;
+    offsets
+        .iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let end = offsets.get(i + 1).copied().unwrap_or(data.len());
+            data.get(*start..end)
+                .ok_or_else(|| anyhow!("invalid MOBI record offset"))
+        })
+        .collect()
+}
+
+/// EXTH metadata records by type.
+fn exth(record: &[u8]) -> HashMap<u32, &[u8]> {
+    let mut result = HashMap::new();
+    let Some(header_length) = be32(record, 20) else {
+        return result;
+    };
+    let exth_flags = be32(record, 0x80).unwrap_or(0);
+    let start = 16 + header_length as usize;
+    if exth_flags & 0x40 == 0 || record.get(start..start + 4) != Some(b"EXTH") {
+        return result;
+    }
+    let count = be32(record, start + 8).unwrap_or(0);
+    let mut position = start + 12;
+    for _ in 0..count {
+        let (Some(kind), Some(length)) = (be32(record, position), be32(record, position + 4))
+        else {
+            break;
+        };
+        if let Some(value) = record.get(position + 8..position + length as usize) {
+            result.insert(kind, value);
+        }
+        position += (length as usize).max(8);
+    }
+    result
+}
+
+/// Size of the trailing entries appended to a text record, as announced by the extra
+/// record data flags.
+fn trailing_entries_size(record: &[u8], flags: u16) -> usize {
+    let mut size = 0;
+    let mut bits = flags >> 1;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            let end = record.len().saturating_sub(size);
+            let mut value = 0;
+            for (shift, byte) in record[..end].iter().rev().take(4).enumerate() {
+                value |= ((byte & 0x7F) as usize) << (7 * shift);
+                if byte & 0x80 != 0 {
+                    break;
+                }
+            }
+            size += value;
+        }
+        bits >>= 1;
+    }
+    if flags & 1 != 0 {
+        if let Some(byte) = record.len().checked_sub(size + 1).map(|i| record[i]) {
+            size += (byte & 0x3) as usize + 1;
+        }
+    }
+    size.min(record.len())
+}
+
+/// Decompresses a PalmDOC (LZ77) text record.
+fn decompress(record: &[u8], compression: u16) -> Vec<u8> {
+    if compression == 1 {
+        return record.to_vec();
+    }
+    let mut result = Vec::with_capacity(record.len() * 2);
+    let mut i = 0;
+    while i < record.len() {
+        let byte = record[i];
+        i += 1;
+        match byte {
+            0x01..=0x08 => {
+                let end = (i + byte as usize).min(record.len());
+                result.extend_from_slice(&record[i..end]);
+                i = end;
+            }
+            0x00 | 0x09..=0x7F => result.push(byte),
+            0x80..=0xBF => {
+                let Some(next) = record.get(i) else { break };
+                i += 1;
+                let pair = (((byte as usize) << 8) | *next as usize) & 0x3FFF;
+                let distance = pair >> 3;
+                let length = (pair & 0x7) + 3;
+                if distance == 0 || distance > result.len() {
+                    continue;
+                }
+                for _ in 0..length {
+                    result.push(result[result.len() - distance]);
+                }
+            }
+            0xC0..=0xFF => {
+                result.push(b' ');
+                result.push(byte ^ 0x80);
+            }
+        }
+    }
+    result
+}
+
+struct IndexEntry {
+    tags: HashMap<u8, Vec<u32>>,
+}
+
+/// Reads the entries of the index starting at record `index`, along with the records
+/// holding its strings (CNCX).
+fn read_index<'a>(records: &[&'a [u8]], index: usize) -> Option<(Vec<IndexEntry>, Vec<&'a [u8]>)> {
+    let header = records.get(index)?;
+    if header.get(..4)? != b"INDX" {
+        return None;
+    }
+    let tagx_start = be32(header, 4)? as usize;
+    let index_records = be32(header, 24)? as usize;
+    let cncx_records = be32(header, 52)? as usize;
+    if header.get(tagx_start..tagx_start + 4)? != b"TAGX" {
+        return None;
+    }
+    let tagx_length = be32(header, tagx_start + 4)? as usize;
+    let control_bytes = be32(header, tagx_start + 8)? as usize;
+    let tagx: Vec<&[u8]> = header
+        .get(tagx_start + 12..tagx_start + tagx_length)?
+        .chunks_exact(4)
+        .collect();
+
+    let mut entries = vec![];
+    for record in records.get(index + 1..index + 1 + index_records)? {
+        let idxt = be32(record, 20)? as usize;
+        let count = be32(record, 24)? as usize;
+        let offsets: Vec<usize> = (0..count)
+            .map(|i| be16(record, idxt + 4 + i * 2).map(|o| o as usize))
+            .collect::<Option<_>>()?;
+        for (i, start) in offsets.iter().enumerate() {
+            let end = offsets.get(i + 1).copied().unwrap_or(idxt);
+            let entry = record.get(*start..end)?;
+            let ident_length = *entry.first()? as usize;
+            let data = entry.get(1 + ident_length..)?;
+            entries.push(IndexEntry {
+                tags: tag_map(control_bytes, &tagx, data)?,
+            });
+        }
+    }
+    let cncx = records
+        .iter()
+        .skip(index + 1 + index_records)
+        .take(cncx_records)
+        .copied()
+        .collect();
+    Some((entries, cncx))
+}
+
+/// Decodes the tag values of an index entry following the TAGX table.
+fn tag_map(
+    control_byte_count: usize,
+    tagx: &[&[u8]],
+    data: &[u8],
+) -> Option<HashMap<u8, Vec<u32>>> {
+    let mut control_bytes = data.get(..control_byte_count)?.iter();
+    let mut control_byte = *control_bytes.next()?;
+    let mut data = &data[control_byte_count..];
+    let mut present = vec![];
+    for entry in tagx {
+        let (tag, values_per_entry, mask, end) = (entry[0], entry[1], entry[2], entry[3]);
+        if end == 1 {
+            control_byte = control_bytes.next().copied().unwrap_or(0);
+            continue;
+        }
+        let value = control_byte & mask;
+        if value == 0 {
+            continue;
+        }
+        if value == mask && mask.count_ones() > 1 {
+            let (bytes, consumed) = forward_varint(data)?;
+            data = &data[consumed..];
+            present.push((tag, None, Some(bytes as usize)));
+        } else {
+            let count = value >> mask.trailing_zeros();
+            present.push((tag, Some(count as usize * values_per_entry as usize), None));
+        }
+    }
+
+    let mut result = HashMap::new();
+    for (tag, count, bytes) in present {
+        let mut values = vec![];
+        if let Some(count) = count {
+            for _ in 0..count {
+                let (value, consumed) = forward_varint(data)?;
+                data = &data[consumed..];
+                values.push(value);
+            }
+        } else if let Some(bytes) = bytes {
+            let mut total = 0;
+            while total < bytes {
+                let (value, consumed) = forward_varint(data)?;
+                data = &data[consumed..];
+                total += consumed;
+                values.push(value);
+            }
+        }
+        result.insert(tag, values);
+    }
+    Some(result)
+}
+
+/// Table of contents headings from the NCX index entries, resolving their section with
+/// `section`.
+fn ncx_headings(
+    (entries, cncx): &(Vec<IndexEntry>, Vec<&[u8]>),
+    utf8: bool,
+    section: impl Fn(&IndexEntry) -> Option<usize>,
+) -> Vec<(usize, usize, String)> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let label = *entry.tags.get(&3)?.first()? as usize;
+            let record = cncx.get(label >> 16)?;
+            let (length, consumed) = forward_varint(record.get(label & 0xFFFF..)?)?;
+            let start = (label & 0xFFFF) + consumed;
+            let name = decode(record.get(start..start + length as usize)?, utf8);
+            let level = entry
+                .tags
+                .get(&4)
+                .and_then(|d| d.first())
+                .map(|d| *d as usize + 1)
+                .unwrap_or(1);
+            Some((level, section(entry)?, name))
+        })
+        .collect()
+}
+
+/// Decodes the text between consecutive section starts. The starts come from the book's
+/// indexes, so those out of order or past the end of the text give empty sections.
+fn split_sections(text: &[u8], starts: &[usize], utf8: bool) -> Vec<String> {
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let end = starts
+                .get(i + 1)
+                .copied()
+                .unwrap_or(text.len())
+                .min(text.len());
+            decode(&text[(*start).min(end)..end], utf8)
+        })
+        .collect()
+}
+
+fn split_offsets(text: &[u8], separator: &str) -> Vec<usize> {
+    let mut starts: Vec<usize> = Regex::new(separator)
+        .unwrap()
+        .find_iter(text)
+        .map(|m| m.start())
+        .filter(|s| *s > 0)
+        .collect();
+    starts.insert(0, 0);
+    starts
+}
+
+/// Variable width integer whose last byte has the high bit set.
+fn forward_varint(data: &[u8]) -> Option<(u32, usize)> {
+    let mut value: u32 = 0;
+    for (i, byte) in data.iter().take(5).enumerate() {
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 != 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn decode(text: &[u8], utf8: bool) -> String {
+    let encoding = if utf8 { UTF_8 } else { WINDOWS_1252 };
+    encoding.decode(text).0.into_owned()
+}
+
+fn be16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_decompresses_palmdoc_records() {
+        check!(decompress(b"abc\x80\x18\xe1\x02\xc0\xc1", 2) == b"abcabc a\xc0\xc1");
+    }
+
+    #[rstest]
+    fn it_strips_trailing_entries() {
+        check!(trailing_entries_size(b"text\x00\x82", 0b10) == 2);
+        check!(trailing_entries_size(b"text\x01", 0b1) == 2);
+    }
+
+    #[rstest]
+    fn it_splits_sections_on_page_breaks() {
+        let mut doc = MobiDoc::parse(&mobi(0, b"<p>One</p><mbp:pagebreak/><p>Two</p>")).unwrap();
+
+        check!(doc.sections() == 2);
+        check!(doc.metadata().title.as_deref() == Some("Test Book"));
+        let_assert!(Some(second) = doc.section(1));
+        check!(String::from_utf8(second).unwrap() == "<mbp:pagebreak/><p>Two</p>");
+    }
+
+    #[rstest]
+    fn it_rejects_drm_protected_books() {
+        let_assert!(Err(error) = MobiDoc::parse(&mobi(2, b"<p>secret</p>")));
+        check!(error.to_string().contains("DRM"));
+    }
+
+    #[rstest]
+    fn it_ignores_offsets_past_the_text() {
+        let sections = split_sections(b"<p>One</p><p>Two</p>", &[0, 10, 40, 30], true);
+        check!(sections == vec!["<p>One</p>", "<p>Two</p>", "", ""]);
+    }
+
+    #[rstest]
+    fn it_ignores_corrupted_header_fields() {
+        let mut data = mobi(0, b"<p>One</p>");
+        let name_field = 78 + 2 * 8 + 0x54;
+        data[name_field..name_field + 4].copy_from_slice(&(u32::MAX - 4).to_be_bytes());
+        let_assert!(Ok(doc) = MobiDoc::parse(&data));
+        check!(doc.metadata().title == None);
+
+        // The text record announces more text than the file holds.
+        data.truncate(data.len() - 4);
+        let_assert!(Ok(mut doc) = MobiDoc::parse(&data));
+        check!(doc.section(0) == Some(b"<p>One".to_vec()));
+    }
+
+    #[rstest]
+    fn it_rejects_other_files() {
+        check!(MobiDoc::parse(b"not a book").is_err());
+    }
+
+    /// Builds an uncompressed MOBI book with a single text record.
+    fn mobi(encryption: u16, text: &[u8]) -> Vec<u8> {
+        let name = b"Test Book";
+        let mut header = vec![0u8; 16 + 0xF0];
+        header[0..2].copy_from_slice(&1u16.to_be_bytes());
+        header[4..8].copy_from_slice(&(text.len() as u32).to_be_bytes());
+        header[8..10].copy_from_slice(&1u16.to_be_bytes());
+        header[12..14].copy_from_slice(&encryption.to_be_bytes());
+        header[16..20].copy_from_slice(b"MOBI");
+        header[20..24].copy_from_slice(&0xF0u32.to_be_bytes());
+        header[0x1C..0x20].copy_from_slice(&65001u32.to_be_bytes());
+        header[0x24..0x28].copy_from_slice(&6u32.to_be_bytes());
+        let name_offset = header.len() as u32;
+        header[0x54..0x58].copy_from_slice(&name_offset.to_be_bytes());
+        header[0x58..0x5C].copy_from_slice(&(name.len() as u32).to_be_bytes());
+        for offset in [0xC0, 0xF4, 0xF8, 0xFC] {
+            header[offset..offset + 4].copy_from_slice(&NO_INDEX.to_be_bytes());
+        }
+        header.extend_from_slice(name);
+
+        let mut data = vec![0u8; 78];
+        data[60..68].copy_from_slice(b"BOOKMOBI");
+        data[76..78].copy_from_slice(&2u16.to_be_bytes());
+        let first = 78 + 2 * 8;
+        data.extend_from_slice(&(first as u32).to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&((first + header.len()) as u32).to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&header);
+        data.extend_from_slice(text);
+        data
+    }
+}
@@ -0,0 +1,389 @@
+use std::{collections::HashMap, fs::File, io::Read, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use roxmltree::Node;
+use zip::ZipArchive;
+
+use super::{
+    content_identifier, escape_html, toc_from_headings, Document, Metadata, TableOfContentNode,
+};
+
+/// Word (`.docx`) or OpenDocument (`.odt`) text document, split into one section per
+/// top-level heading.
+pub struct OfficeDoc {
+    sections: Vec<String>,
+    toc: Vec<TableOfContentNode>,
+    identifier: String,
+    title: Option<String>,
+    author: Option<String>,
+}
+
+/// A paragraph of the document, with its heading level if it is a heading.
+struct Paragraph {
+    level: Option<usize>,
+    text: String,
+}
+
+impl OfficeDoc {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let (paragraphs, properties) = if extension == "odt" {
+            let content = entry(&mut archive, "content.xml")?
+                .ok_or_else(|| anyhow!("no content.xml found in {}", path.display()))?;
+            let meta = entry(&mut archive, "meta.xml")?;
+            (odt_paragraphs(&content)?, meta.map(|m| properties(&m)))
+        } else {
+            let content = entry(&mut archive, "word/document.xml")?
+                .ok_or_else(|| anyhow!("no word/document.xml found in {}", path.display()))?;
+            let styles = entry(&mut archive, "word/styles.xml")?;
+            let core = entry(&mut archive, "docProps/core.xml")?;
+            let styles = styles.map(|s| heading_styles(&s)).unwrap_or_default();
+            (
+                docx_paragraphs(&content, &styles)?,
+                core.map(|c| properties(&c)),
+            )
+        };
+        let properties = properties.unwrap_or_default();
+
+        let mut doc = Self::from_paragraphs(paragraphs);
+        doc.author = properties
+            .get("initial-creator")
+            .or(properties.get("creator"))
+            .cloned();
+        // The creation date and author survive edits, so a revised copy keeps its place.
+        if let Some(created) = properties
+            .get("created")
+            .or(properties.get("creation-date"))
+        {
+            let creator = properties
+                .get("creator")
+                .or(properties.get("initial-creator"))
+                .cloned()
+                .unwrap_or_default();
+            doc.identifier = content_identifier(format!("{creator}\n{created}").as_bytes());
+        }
+        doc.title = properties
+            .get("title")
+            .cloned()
+            .or(doc.title)
+            .or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()));
+        Ok(doc)
+    }
+
+    fn from_paragraphs(paragraphs: Vec<Paragraph>) -> Self {
+        let top_level = paragraphs.iter().filter_map(|p| p.level).min();
+        let mut sections = vec![String::new()];
+        let mut toc = vec![];
+        for paragraph in &paragraphs {
+            let text = escape_html(&paragraph.text);
+            match paragraph.level {
+                Some(level) => {
+                    if Some(level) == top_level && !sections.last().unwrap().is_empty() {
+                        sections.push(String::new());
+                    }
+                    toc.push((level, sections.len() - 1, paragraph.text.clone()));
+                    let tag = format!("h{}", level.min(6));
+                    sections
+                        .last_mut()
+                        .unwrap()
+                        .push_str(&format!("<{tag}>{text}</{tag}>"));
+                }
+                None => sections
+                    .last_mut()
+                    .unwrap()
+                    .push_str(&format!("<p>{text}</p>")),
+            }
+        }
+
+        let content: String = paragraphs.iter().map(|p| p.text.as_str()).collect();
+        Self {
+            title: toc
+                .iter()
+                .find(|(level, _, _)| Some(*level) == top_level)
+                .map(|(_, _, name)| name.clone()),
+            toc: toc_from_headings(&toc),
+            identifier: content_identifier(content.as_bytes()),
+            author: None,
+            sections,
+        }
+    }
+}
+
+impl Document for OfficeDoc {
+    fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        self.sections.get(index).map(|s| s.as_bytes().to_vec())
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            author: self.author.clone(),
+        }
+    }
+}
+
+fn entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => bail!(e),
+    };
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(Some(content))
+}
+
+/// Heading level of the paragraph styles of a Word document, by style identifier.
+fn heading_styles(styles: &str) -> HashMap<String, usize> {
+    let Ok(xml) = roxmltree::Document::parse(styles) else {
+        return HashMap::new();
+    };
+    xml.root_element()
+        .children()
+        .filter(|s| s.tag_name().name() == "style")
+        .filter_map(|style| {
+            let id = attribute(style, "styleId")?;
+            let level = outline_level(style).unwrap_or_else(|| {
+                let name = attribute(descendant(style, "name")?, "val")?.to_lowercase();
+                name.strip_prefix("heading ")?.parse().ok()
+            })?;
+            Some((id.to_string(), level))
+        })
+        .collect()
+}
+
+/// Heading level set by the `outlineLvl` of paragraph properties, if there is one: levels
+/// 0 to 8 are headings, 9 is body text.
+fn outline_level(properties: Node) -> Option<Option<usize>> {
+    let level: usize = attribute(descendant(properties, "outlineLvl")?, "val")?
+        .parse()
+        .ok()?;
+    Some((level < 9).then_some(level + 1))
+}
+
+fn docx_paragraphs(content: &str, styles: &HashMap<String, usize>) -> Result<Vec<Paragraph>> {
+    let xml = roxmltree::Document::parse(content)?;
+    Ok(xml
+        .descendants()
+        .filter(|n| {
+            n.tag_name().name() == "p" && !n.ancestors().skip(1).any(|a| a.tag_name().name() == "p")
+        })
+        .map(|p| {
+            let properties = p.children().find(|c| c.tag_name().name() == "pPr");
+            let level = properties.and_then(|properties| {
+                outline_level(properties).unwrap_or_else(|| {
+                    let style = attribute(descendant(properties, "pStyle")?, "val")?;
+                    styles
+                        .get(style)
+                        .copied()
+                        .or_else(|| style.strip_prefix("Heading").and_then(|l| l.parse().ok()))
+                })
+            });
+            let text = p
+                .descendants()
+                .filter_map(|n| match n.tag_name().name() {
+                    "t" => n.text(),
+                    "tab" | "br" | "cr" => Some(" "),
+                    _ => None,
+                })
+                .collect();
+            Paragraph { level, text }
+        })
+        .filter(|p| !p.text.trim().is_empty())
+        .collect())
+}
+
+fn odt_paragraphs(content: &str) -> Result<Vec<Paragraph>> {
+    let xml = roxmltree::Document::parse(content)?;
+    let body = descendant(xml.root_element(), "body")
+        .and_then(|b| b.children().find(|c| c.tag_name().name() == "text"))
+        .ok_or_else(|| anyhow!("no text body found in content.xml"))?;
+    Ok(body
+        .descendants()
+        .filter(|n| matches!(n.tag_name().name(), "p" | "h"))
+        .filter(|n| {
+            !n.ancestors()
+                .skip(1)
+                .any(|a| matches!(a.tag_name().name(), "p" | "h"))
+        })
+        .map(|p| {
+            let level = (p.tag_name().name() == "h").then(|| {
+                attribute(p, "outline-level")
+                    .and_then(|l| l.parse().ok())
+                    .unwrap_or(1)
+            });
+            let mut text = String::new();
+            odt_text(p, &mut text);
+            Paragraph { level, text }
+        })
+        .filter(|p| !p.text.trim().is_empty())
+        .collect())
+}
+
+fn odt_text(node: Node, text: &mut String) {
+    for child in node.children() {
+        if child.is_text() {
+            text.push_str(child.text().unwrap_or_default());
+            continue;
+        }
+        match child.tag_name().name() {
+            "s" => {
+                let count = attribute(child, "c")
+                    .and_then(|c| c.parse().ok())
+                    .unwrap_or(1);
+                text.push_str(&" ".repeat(count));
+            }
+            "tab" | "line-break" => text.push(' '),
+            "note" | "annotation" => {}
+            _ => odt_text(child, text),
+        }
+    }
+}
+
+/// Core document properties (`docProps/core.xml` or `meta.xml`) by their local name.
+fn properties(content: &str) -> HashMap<String, String> {
+    let Ok(xml) = roxmltree::Document::parse(content) else {
+        return HashMap::new();
+    };
+    xml.descendants()
+        .filter(|n| n.is_element() && n.children().all(|c| c.is_text()))
+        .filter_map(|n| {
+            let value = n.text()?.trim();
+            (!value.is_empty()).then(|| (n.tag_name().name().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn descendant<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.descendants().find(|n| n.tag_name().name() == name)
+}
+
+/// Attribute by local name, whatever its namespace.
+fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes()
+        .find(|a| a.name() == name)
+        .map(|a| a.value())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+    use std::io::Write;
+
+    #[rstest]
+    fn it_reads_word_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = archive(
+            dir.path(),
+            "spec.docx",
+            &[
+                ("word/document.xml", DOCX),
+                ("word/styles.xml", DOCX_STYLES),
+                ("docProps/core.xml", DOCX_CORE),
+            ],
+        );
+
+        let mut doc = OfficeDoc::open(&path).unwrap();
+        check!(doc.sections() == 3);
+        check!(doc.metadata().title.as_deref() == Some("Storage spec"));
+        check!(doc.metadata().author.as_deref() == Some("Ada"));
+        let_assert!(Some(first) = doc.section(1));
+        check!(
+            String::from_utf8(first).unwrap()
+                == "<h1>Goals</h1><p>Keep it small.</p><h2>Non goals</h2>"
+        );
+        let toc = doc.table_of_contents();
+        check!(toc.len() == 2);
+        check!(toc[0].children[0].name == "Non goals");
+        check!(toc[1].name == "Design");
+        check!(toc[1].index == 2);
+    }
+
+    #[rstest]
+    fn it_keeps_the_identifier_across_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("word/document.xml", DOCX),
+            ("docProps/core.xml", DOCX_CORE),
+        ];
+        let original = OfficeDoc::open(&archive(dir.path(), "original.docx", &files)).unwrap();
+        let edited = DOCX.replace("Keep it small.", "Keep it smaller.");
+        let files = [("word/document.xml", edited.as_str()), files[1]];
+        let edited = OfficeDoc::open(&archive(dir.path(), "edited.docx", &files)).unwrap();
+
+        check!(original.identifier() == edited.identifier());
+    }
+
+    #[rstest]
+    fn it_reads_open_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = archive(dir.path(), "notes.odt", &[("content.xml", ODT)]);
+
+        let mut doc = OfficeDoc::open(&path).unwrap();
+        check!(doc.sections() == 2);
+        check!(doc.metadata().title.as_deref() == Some("Meeting"));
+        let_assert!(Some(first) = doc.section(0));
+        check!(String::from_utf8(first).unwrap() == "<h1>Meeting</h1><p>Agreed   on dates</p>");
+        check!(doc.table_of_contents()[1].name == "Actions");
+    }
+
+    fn archive(dir: &Path, name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, Default::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    const DOCX: &str = r#"<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:body>
+    <w:p><w:pPr><w:outlineLvl w:val="9"/></w:pPr><w:r><w:t>Draft, do not share.</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="Heading1"/></w:pPr><w:r><w:t>Goals</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="BodyText"/></w:pPr><w:r><w:t xml:space="preserve">Keep it </w:t></w:r><w:r><w:t>small.</w:t></w:r></w:p>
+    <w:p><w:pPr><w:pStyle w:val="Subheading"/></w:pPr><w:r><w:t>Non goals</w:t></w:r></w:p>
+    <w:p><w:pPr><w:outlineLvl w:val="0"/></w:pPr><w:r><w:t>Design</w:t></w:r></w:p>
+    <w:p/>
+  </w:body>
+</w:document>"#;
+
+    const DOCX_STYLES: &str = r#"<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+  <w:style w:type="paragraph" w:styleId="Subheading"><w:name w:val="heading 2"/></w:style>
+  <w:style w:type="paragraph" w:styleId="BodyText"><w:name w:val="Body Text"/><w:pPr><w:outlineLvl w:val="9"/></w:pPr></w:style>
+</w:styles>"#;
+
+    const DOCX_CORE: &str = r#"<cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/">
+  <dc:title>Storage spec</dc:title>
+  <dc:creator>Ada</dc:creator>
+  <dcterms:created>2023-09-01T10:00:00Z</dcterms:created>
+</cp:coreProperties>"#;
+
+    const ODT: &str = r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0">
+  <office:body><office:text>
+    <text:h text:outline-level="1">Meeting</text:h>
+    <text:p>Agreed<text:s text:c="3"/>on<text:tab/>dates<text:note><text:note-body><text:p>a note</text:p></text:note-body></text:note></text:p>
+    <text:h text:outline-level="1">Actions</text:h>
+    <text:list><text:list-item><text:p>Write it down</text:p></text:list-item></text:list>
+  </office:text></office:body>
+</office:document-content>"#;
+}
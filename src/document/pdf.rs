@@ -0,0 +1,393 @@
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+use anyhow::{bail, Result};
+use itertools::Itertools;
+use pdf_extract::PlainTextOutput;
+use regex::Regex;
+
+use super::{
+    content_identifier, text::section_html, toc_from_headings, Document, Metadata,
+    TableOfContentNode,
+};
+
+/// Lines at the top or bottom of a page checked for running headers and footers.
+const EDGE_LINES: usize = 2;
+
+/// Held while the panic hook is swapped out, so concurrent extractions restore the right one.
+static PANIC_HOOK: Mutex<()> = Mutex::new(());
+
+/// Text-based PDF, with one section per page. The outline becomes the table of contents.
+pub struct PdfDoc {
+    sections: Vec<String>,
+    toc: Vec<TableOfContentNode>,
+    identifier: String,
+    title: Option<String>,
+    author: Option<String>,
+}
+
+impl PdfDoc {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut doc = Self::parse(&std::fs::read(path)?)?;
+        if doc.title.is_none() {
+            doc.title = path.file_stem().map(|s| s.to_string_lossy().to_string());
+        }
+        Ok(doc)
+    }
+
+    /// Reads the text of a PDF. Malformed files can make the extraction panic, which is
+    /// turned into an error without running the panic hook, as the terminal is still in use.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let _lock = PANIC_HOOK.lock().unwrap_or_else(|e| e.into_inner());
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+        let result = std::panic::catch_unwind(|| Self::extract(data));
+        std::panic::set_hook(hook);
+        result.unwrap_or_else(|_| bail!("the PDF is malformed and its text cannot be extracted"))
+    }
+
+    fn extract(data: &[u8]) -> Result<Self> {
+        let mut pdf = pdf_extract::Document::load_mem(data)?;
+        if pdf.is_encrypted() && pdf.decrypt("").is_err() {
+            bail!("the PDF is password protected and cannot be opened");
+        }
+        let pages = pdf
+            .get_pages()
+            .into_keys()
+            .map(|number| {
+                let mut text = String::new();
+                pdf_extract::output_doc_page(&pdf, &mut PlainTextOutput::new(&mut text), number)?;
+                Ok(text)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if pages.iter().all(|p| p.trim().is_empty()) {
+            bail!("the PDF has no text layer");
+        }
+
+        let toc: Vec<(usize, usize, String)> = pdf
+            .get_toc()
+            .map(|toc| {
+                toc.toc
+                    .into_iter()
+                    .map(|entry| (entry.level, entry.page.saturating_sub(1), entry.title))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let info = |key: &[u8]| {
+            pdf.trailer
+                .get(b"Info")
+                .and_then(|info| pdf.dereference(info))
+                .and_then(|(_, info)| info.as_dict())
+                .and_then(|info| info.get(key))
+                .ok()
+                .and_then(|value| pdf_extract::decode_text_string(value).ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let title = info(b"Title");
+        let author = info(b"Author");
+
+        Ok(Self {
+            sections: clean_pages(&pages)
+                .iter()
+                .map(|lines| section_html(None, &lines.iter().map(String::as_str).collect_vec()))
+                .collect(),
+            toc: toc_from_headings(&toc),
+            identifier: content_identifier(data),
+            title,
+            author,
+        })
+    }
+}
+
+impl Document for PdfDoc {
+    fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        self.sections.get(index).map(|s| s.as_bytes().to_vec())
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            author: self.author.clone(),
+        }
+    }
+}
+
+/// Lines of every page without page numbers and running headers and footers, with
+/// words hyphenated across line breaks joined back together.
+fn clean_pages(pages: &[String]) -> Vec<Vec<String>> {
+    let page_number =
+        Regex::new(r"(?i)^[\s\-–—]*(page\s+)?(\d+|[ivxlcdm]+)(\s*(of|/)\s*\d+)?[\s\-–—]*$")
+            .unwrap();
+    let roman =
+        Regex::new(r"(?i)^m{0,3}(cm|cd|d?c{0,3})(xc|xl|l?x{0,3})(ix|iv|v?i{0,3})$").unwrap();
+    // Roman numerals are also words, like `mix` or `civil`, so they must be well formed
+    // and on the first or last line of the page.
+    let is_page_number = |page: &[&str], i: usize| {
+        let Some(number) = page_number.captures(page[i]).map(|c| c[2].to_string()) else {
+            return false;
+        };
+        let filled = |l: &&str| !l.trim().is_empty();
+        let edges = [page.iter().position(filled), page.iter().rposition(filled)];
+        number.starts_with(|c: char| c.is_ascii_digit())
+            || (roman.is_match(&number) && edges.contains(&Some(i)))
+    };
+    let pages: Vec<Vec<&str>> = pages
+        .iter()
+        .map(|p| p.lines().map(str::trim_end).collect())
+        .collect();
+
+    // Headers and footers repeat on many pages, usually with a changing page number.
+    let mut repeated: HashMap<String, usize> = HashMap::new();
+    for page in &pages {
+        let keys = edge_lines(page).into_iter().map(|i| running_key(page[i]));
+        for key in keys.unique() {
+            *repeated.entry(key).or_default() += 1;
+        }
+    }
+    let is_running = |line: &str| {
+        repeated
+            .get(&running_key(line))
+            .is_some_and(|count| *count >= 3 && *count * 3 >= pages.len())
+    };
+
+    pages
+        .iter()
+        .map(|page| {
+            let dropped: Vec<usize> = edge_lines(page)
+                .into_iter()
+                .filter(|i| is_page_number(page, *i) || is_running(page[*i]))
+                .collect();
+            let lines = page
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !dropped.contains(i))
+                .map(|(_, l)| *l);
+            join_hyphenated(lines)
+        })
+        .collect()
+}
+
+/// Positions of the first and last non-blank lines of a page.
+fn edge_lines(page: &[&str]) -> Vec<usize> {
+    let filled: Vec<usize> = (0..page.len())
+        .filter(|i| !page[*i].trim().is_empty())
+        .collect();
+    filled
+        .iter()
+        .take(EDGE_LINES)
+        .chain(filled.iter().rev().take(EDGE_LINES))
+        .copied()
+        .unique()
+        .collect()
+}
+
+/// Joins a word split by a hyphen at the end of a line with its remainder on the next
+/// line, keeping the rest of that line where it is.
+fn join_hyphenated<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    let mut hyphenated = false;
+    for line in lines {
+        let mut line = line.to_string();
+        let rest = line.trim_start();
+        if hyphenated && rest.starts_with(char::is_lowercase) {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let previous = result.last_mut().unwrap();
+            previous.pop();
+            previous.push_str(&rest[..end]);
+            line = rest[end..].trim_start().to_string();
+        }
+        let word = line.rsplit(char::is_whitespace).next().unwrap_or_default();
+        hyphenated = word
+            .strip_suffix('-')
+            .is_some_and(|w| w.chars().count() > 1 && w.ends_with(char::is_alphabetic));
+        result.push(line);
+    }
+    result
+}
+
+/// A line with its numbers masked, so that headers only differing by page number match.
+fn running_key(line: &str) -> String {
+    line.split_whitespace()
+        .map(|w| w.replace(|c: char| c.is_ascii_digit(), "#"))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use pdf_extract::{
+        content::{Content, Operation},
+        dictionary, Object, Stream,
+    };
+    use rstest::*;
+    use std::cell::Cell;
+
+    #[rstest]
+    fn it_joins_hyphenated_words() {
+        let lines =
+            join_hyphenated(["The speed of read-", "ing is well-", "Known to vary."].into_iter());
+
+        check!(lines == vec!["The speed of reading", "is well-", "Known to vary."]);
+    }
+
+    #[rstest]
+    fn it_drops_running_headers_and_page_numbers() {
+        let pages: Vec<String> = ["one", "two", "three", "four"]
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                format!(
+                    "A Paper on Reading {}\n\nPage {n} starts.\nPage {n} ends.\n\n- {} -\n",
+                    i + 1,
+                    i + 1
+                )
+            })
+            .collect();
+
+        let pages = clean_pages(&pages);
+        check!(pages[1] == vec!["", "Page two starts.", "Page two ends.", ""]);
+    }
+
+    #[rstest]
+    #[case("xiv\nText.\nMore text.\n", vec!["Text.", "More text."])]
+    #[case("Text.\nMore text.\n- ix -\n", vec!["Text.", "More text."])]
+    #[case("Text.\nmix\nMore text.\n", vec!["Text.", "mix", "More text."])]
+    #[case("civil\nText.\ndid\n", vec!["civil", "Text.", "did"])]
+    fn it_only_drops_well_formed_roman_page_numbers(
+        #[case] page: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        let pages = clean_pages(&[page.to_string()]);
+        check!(pages[0] == expected);
+    }
+
+    #[rstest]
+    fn it_reads_pages_and_outline() {
+        let mut doc = PdfDoc::parse(&pdf(&["First page", "Second page"], "Type1")).unwrap();
+
+        check!(doc.sections() == 2);
+        check!(doc.metadata().title.as_deref() == Some("Reading Paper"));
+        check!(doc.metadata().author.as_deref() == Some("A. Reader"));
+        let_assert!(Some(second) = doc.section(1));
+        check!(String::from_utf8(second).unwrap().contains("Second page"));
+        let toc = doc.table_of_contents();
+        check!(toc.len() == 2);
+        check!(toc[1].name == "Results");
+        check!(toc[1].index == 1);
+    }
+
+    #[rstest]
+    fn it_reports_extraction_panics() {
+        thread_local! {
+            static HOOK_CALLED: Cell<bool> = const { Cell::new(false) };
+        }
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| HOOK_CALLED.with(|called| called.set(true))));
+
+        // Type0 fonts without descendant fonts make the extraction panic.
+        let result = PdfDoc::parse(&pdf(&["First page", "Second page"], "Type0"));
+        std::panic::set_hook(hook);
+
+        let_assert!(Err(error) = result);
+        check!(error.to_string().contains("malformed"));
+        check!(!HOOK_CALLED.with(Cell::get));
+    }
+
+    /// Builds a PDF with one line of text per page in a font of the given subtype, and an
+    /// outline entry per page.
+    fn pdf(texts: &[&str], font_subtype: &str) -> Vec<u8> {
+        let mut doc = pdf_extract::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let outlines_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => font_subtype,
+            "BaseFont" => "Helvetica",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let mut kids = vec![];
+        for text in texts {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![100.into(), 600.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            kids.push(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            }));
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids.iter().map(|k| Object::Reference(*k)).collect::<Vec<_>>(),
+                "Count" => kids.len() as i64,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let first_id = doc.new_object_id();
+        let second_id = doc.new_object_id();
+        for (id, title, page, sibling) in [
+            (first_id, "Introduction", kids[0], ("Next", second_id)),
+            (second_id, "Results", kids[1], ("Prev", first_id)),
+        ] {
+            doc.objects.insert(
+                id,
+                Object::Dictionary(dictionary! {
+                    "Title" => Object::string_literal(title),
+                    "Parent" => outlines_id,
+                    "Dest" => vec![page.into(), "Fit".into()],
+                    sibling.0 => sibling.1,
+                }),
+            );
+        }
+        doc.objects.insert(
+            outlines_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Outlines",
+                "First" => first_id,
+                "Last" => second_id,
+                "Count" => 2,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Outlines" => outlines_id,
+        });
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::string_literal("Reading Paper"),
+            "Author" => Object::string_literal("A. Reader"),
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc.trailer.set("Info", info_id);
+        let mut data = vec![];
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+}
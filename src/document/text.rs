@@ -0,0 +1,292 @@
+use std::{io::Read, path::Path};
+
+use anyhow::Result;
+use regex::Regex;
+
+use super::{
+    content_identifier, escape_html, toc_from_headings, Document, Metadata, TableOfContentNode,
+};
+
+/// Plain text document, with special handling for Project Gutenberg releases.
+pub struct TextDoc {
+    sections: Vec<String>,
+    toc: Vec<TableOfContentNode>,
+    identifier: String,
+    title: Option<String>,
+    author: Option<String>,
+}
+
+impl TextDoc {
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)?;
+        let mut doc = Self::parse(&String::from_utf8_lossy(&content));
+        if doc.title.is_none() {
+            doc.title = path.file_stem().map(|s| s.to_string_lossy().to_string());
+        }
+        Ok(doc)
+    }
+
+    /// Reads a whole stream, such as a pipe, dropping the terminal formatting that
+    /// tools like `man` leave in their output.
+    pub fn read(mut input: impl Read) -> Result<Self> {
+        let mut content = vec![];
+        input.read_to_end(&mut content)?;
+        let content = String::from_utf8_lossy(&content);
+        let content = Regex::new(r"(?s).\x08|\x1b\[[0-9;]*m")
+            .unwrap()
+            .replace_all(&content, "");
+        Ok(Self::parse(&content))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let (header, body) = split_gutenberg_boilerplate(content);
+        let identifier = gutenberg_number(header)
+            .map(|n| format!("gutenberg-{n}"))
+            .unwrap_or_else(|| content_identifier(content.as_bytes()));
+        let field = |name: &str| {
+            Regex::new(&format!(r"(?m)^{name}:\s*(.+?)\s*$"))
+                .unwrap()
+                .captures(header)
+                .map(|c| c[1].to_string())
+        };
+        let title = field("Title");
+        let author = field("Author");
+
+        let lines: Vec<&str> = body.lines().collect();
+        let headings = headings(&lines);
+        let mut sections = vec![];
+        let mut toc = vec![];
+        if headings.is_empty() {
+            sections = split_on_blank_runs(&lines)
+                .into_iter()
+                .map(|l| section_html(None, l))
+                .collect();
+        } else {
+            let preamble = &lines[..headings[0].0];
+            if !is_blank(preamble) {
+                sections.push(section_html(None, preamble));
+            }
+            for (i, (start, level)) in headings.iter().enumerate() {
+                let end = headings.get(i + 1).map(|h| h.0).unwrap_or(lines.len());
+                let name = lines[*start].trim();
+                toc.push((*level, sections.len(), name.to_string()));
+                sections.push(section_html(Some(name), &lines[start + 1..end]));
+            }
+        }
+
+        Self {
+            sections,
+            toc: toc_from_headings(&toc),
+            identifier,
+            title,
+            author,
+        }
+    }
+}
+
+impl Document for TextDoc {
+    fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    fn section(&mut self, index: usize) -> Option<Vec<u8>> {
+        self.sections.get(index).map(|s| s.as_bytes().to_vec())
+    }
+
+    fn table_of_contents(&self) -> &[TableOfContentNode] {
+        &self.toc
+    }
+
+    fn identifier(&self) -> String {
+        self.identifier.clone()
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            title: self.title.clone(),
+            author: self.author.clone(),
+        }
+    }
+}
+
+/// Splits a Project Gutenberg release into its license header and the actual text,
+/// dropping the license footer. Other texts are returned whole as body.
+fn split_gutenberg_boilerplate(content: &str) -> (&str, &str) {
+    let start = Regex::new(r"(?im)^\*{3}\s*START OF (THE|THIS) PROJECT GUTENBERG.*$").unwrap();
+    let end = Regex::new(
+        r"(?im)^(\*{3}\s*END OF (THE|THIS) PROJECT GUTENBERG|End of (the )?Project Gutenberg)",
+    )
+    .unwrap();
+    let Some(start) = start.find(content) else {
+        return ("", content);
+    };
+    let (header, body) = content.split_at(start.end());
+    let body = end.find(body).map(|m| &body[..m.start()]).unwrap_or(body);
+    (header, body)
+}
+
+fn gutenberg_number(header: &str) -> Option<String> {
+    Regex::new(r"(?i)e-?book\s*#(\d+)")
+        .unwrap()
+        .captures(header)
+        .map(|c| c[1].to_string())
+}
+
+/// Finds chapter-like headings standing on their own line, returning their line index
+/// and nesting level. Headings without any text before the next one of the same level,
+/// like the entries of a contents listing, are dropped.
+fn headings(lines: &[&str]) -> Vec<(usize, usize)> {
+    let heading =
+        Regex::new(r"^(CHAPTER|Chapter|PART|Part|BOOK|Book)\s+([IVXLCDM]+|\d+)\b").unwrap();
+    let candidates: Vec<(usize, usize)> = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, l)| {
+            l.len() < 80
+                && (*i == 0 || lines[i - 1].trim().is_empty())
+                && lines.get(i + 1).is_none_or(|n| n.trim().is_empty())
+        })
+        .filter_map(|(i, l)| {
+            let kind = heading.captures(l.trim())?.get(1)?.as_str().to_lowercase();
+            Some((i, if kind == "chapter" { 2 } else { 1 }))
+        })
+        .collect();
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|(i, (start, level))| {
+            let next = candidates.get(i + 1);
+            let end = next.map(|h| h.0).unwrap_or(lines.len());
+            !is_blank(&lines[start + 1..end]) || next.is_some_and(|h| h.1 > *level)
+        })
+        .map(|(_, h)| *h)
+        .collect()
+}
+
+/// Splits the text on runs of at least three blank lines.
+fn split_on_blank_runs<'a, 'b>(lines: &'b [&'a str]) -> Vec<&'b [&'a str]> {
+    let mut result = vec![];
+    let mut start = 0;
+    let mut blanks = 0;
+    for (i, l) in lines.iter().enumerate() {
+        if l.trim().is_empty() {
+            blanks += 1;
+            continue;
+        }
+        if blanks >= 3 && !is_blank(&lines[start..i]) {
+            result.push(&lines[start..i]);
+            start = i;
+        }
+        blanks = 0;
+    }
+    if !is_blank(&lines[start..]) {
+        result.push(&lines[start..]);
+    }
+    result
+}
+
+fn is_blank(lines: &[&str]) -> bool {
+    lines.iter().all(|l| l.trim().is_empty())
+}
+
+pub(super) fn section_html(heading: Option<&str>, lines: &[&str]) -> String {
+    let mut html = String::new();
+    if let Some(heading) = heading {
+        html.push_str(&format!("<h2>{}</h2>", escape_html(heading)));
+    }
+    for paragraph in lines
+        .split(|l| l.trim().is_empty())
+        .filter(|p| !p.is_empty())
+    {
+        let paragraph = paragraph
+            .iter()
+            .map(|l| l.trim())
+            .collect::<Vec<_>>()
+            .join(" ");
+        html.push_str(&format!("<p>{}</p>", escape_html(&paragraph)));
+    }
+    html
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_strips_the_gutenberg_boilerplate(gutenberg: &str) {
+        let mut doc = TextDoc::parse(gutenberg);
+
+        check!(doc.identifier() == "gutenberg-11");
+        check!(doc.metadata().title.as_deref() == Some("Alice's Adventures in Wonderland"));
+        let_assert!(Some(last) = doc.section(doc.sections() - 1));
+        let last = String::from_utf8(last).unwrap();
+        check!(!last.contains("license"));
+        let_assert!(Some(first) = doc.section(0));
+        check!(!String::from_utf8(first).unwrap().contains("Release Date"));
+    }
+
+    #[rstest]
+    fn it_splits_on_chapter_headings(gutenberg: &str) {
+        let doc = TextDoc::parse(gutenberg);
+
+        check!(doc.sections() == 3);
+        let toc = doc.table_of_contents();
+        check!(toc.len() == 2);
+        check!(toc[0].name == "CHAPTER I.");
+        check!(toc[0].index == 1);
+        check!(toc[1].name == "CHAPTER II.");
+        check!(toc[1].index == 2);
+    }
+
+    #[rstest]
+    fn it_nests_chapters_in_parts() {
+        let doc = TextDoc::parse(
+            "PART I\n\nIntro.\n\nChapter 1\n\nOne.\n\nChapter 2\n\nTwo.\n\nPART II\n\nChapter 3\n\nThree.\n",
+        );
+
+        let toc = doc.table_of_contents();
+        check!(toc.len() == 2);
+        check!(toc[0].children.len() == 2);
+        check!(toc[1].children.len() == 1);
+        check!(toc[1].children[0].name == "Chapter 3");
+    }
+
+    #[rstest]
+    fn it_reads_terminal_output() {
+        let mut doc = TextDoc::read(
+            "N\x08NA\x08AM\x08ME\x08E\n\n    \x1b[1mgit\x1b[0m - the stupid content tracker\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        let_assert!(Some(section) = doc.section(0));
+        check!(
+            String::from_utf8(section).unwrap()
+                == "<p>NAME</p><p>git - the stupid content tracker</p>"
+        );
+        check!(doc.identifier().len() == 64);
+    }
+
+    #[rstest]
+    fn it_splits_on_blank_runs_without_headings() {
+        let doc = TextDoc::parse("First part.\n\nStill first.\n\n\n\nSecond part.\n");
+
+        check!(doc.sections() == 2);
+        check!(doc.table_of_contents().is_empty());
+    }
+
+    #[fixture]
+    fn gutenberg() -> &'static str {
+        "The Project Gutenberg eBook of Alice's Adventures in Wonderland\n\n\
+         Title: Alice's Adventures in Wonderland\n\n\
+         Release Date: June 27, 2008 [EBook #11]\n\n\
+         *** START OF THE PROJECT GUTENBERG EBOOK ALICE'S ADVENTURES IN WONDERLAND ***\n\n\
+         Contents\n\n CHAPTER I.     Down the Rabbit-Hole\n CHAPTER II.    The Pool of Tears\n\n\n\
+         CHAPTER I.\n\nDown the Rabbit-Hole\n\nAlice was beginning to get very tired\nof sitting by her sister.\n\n\
+         CHAPTER II.\n\nThe Pool of Tears\n\n\u{201c}Curiouser and curiouser!\u{201d} cried Alice.\n\n\
+         *** END OF THE PROJECT GUTENBERG EBOOK ALICE'S ADVENTURES IN WONDERLAND ***\n\n\
+         Full license text.\n"
+    }
+}
@@ -0,0 +1,136 @@
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Byte ranges of the words of a line, in the order they are shown.
+///
+/// Words follow the UAX #29 word boundaries and keep the punctuation around them, so
+/// `"Hello,` stays a single word. Dashes separate words even without spaces, and
+/// punctuation standing on its own is not a word. Chinese and Japanese text, which has
+/// no spaces, keeps one ideograph per word, a kanji taking the hiragana that follows it,
+/// and runs of kana stay together.
+pub fn word_ranges(line: &str) -> Vec<Range<usize>> {
+    let mut words: Vec<Range<usize>> = vec![];
+    let mut current: Option<Word> = None;
+    for (start, segment) in line.split_word_bound_indices() {
+        let end = start + segment.len();
+        if segment.chars().all(char::is_whitespace) {
+            if let Some(word) = current.take() {
+                word.push_to(&mut words);
+            }
+            continue;
+        }
+        let is_word = segment.chars().any(char::is_alphanumeric);
+        match current.as_mut() {
+            Some(word) if !is_word || word.continues_with(segment) => {
+                word.range.end = end;
+                word.has_letters |= is_word;
+                word.broken = is_dash(segment);
+                word.last_script = script(segment).or(word.last_script);
+            }
+            _ => {
+                if let Some(word) = current.take() {
+                    word.push_to(&mut words);
+                }
+                current = Some(Word {
+                    range: start..end,
+                    has_letters: is_word,
+                    broken: is_dash(segment),
+                    last_script: script(segment),
+                });
+            }
+        }
+    }
+    if let Some(word) = current {
+        word.push_to(&mut words);
+    }
+    words
+}
+
+/// A word being built from consecutive segments.
+struct Word {
+    range: Range<usize>,
+    has_letters: bool,
+    /// Whether the last segment was a dash, after which a new word starts.
+    broken: bool,
+    last_script: Option<Script>,
+}
+
+impl Word {
+    fn continues_with(&self, segment: &str) -> bool {
+        if self.broken {
+            return false;
+        }
+        if !self.has_letters {
+            return true;
+        }
+        matches!(
+            (self.last_script, script(segment)),
+            (None, None)
+                | (Some(Script::Han), Some(Script::Hiragana))
+                | (Some(Script::Hiragana), Some(Script::Hiragana))
+                | (Some(Script::Katakana), Some(Script::Katakana))
+        )
+    }
+
+    fn push_to(self, words: &mut Vec<Range<usize>>) {
+        if self.has_letters {
+            words.push(self.range);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Script {
+    Han,
+    Hiragana,
+    Katakana,
+}
+
+/// Script of a segment written without spaces between words, if any.
+fn script(segment: &str) -> Option<Script> {
+    segment.chars().next().and_then(char_script)
+}
+
+/// Whether a character is Chinese or Japanese, written without spaces between words.
+pub fn is_cjk(c: char) -> bool {
+    char_script(c).is_some()
+}
+
+fn char_script(c: char) -> Option<Script> {
+    match c {
+        '\u{3040}'..='\u{309F}' => Some(Script::Hiragana),
+        '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => {
+            Some(Script::Katakana)
+        }
+        '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{3134F}' => Some(Script::Han),
+        _ => None,
+    }
+}
+
+fn is_dash(segment: &str) -> bool {
+    matches!(segment, "—" | "–" | "―" | "⸺" | "⸻")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("Hello, \"world\"!", vec!["Hello,", "\"world\"!"])]
+    #[case("everything—including the well-known", vec!["everything—", "including", "the", "well-known"])]
+    #[case("wait - what?", vec!["wait", "what?"])]
+    #[case("* * *", vec![])]
+    #[case("速读是一种技能。", vec!["速", "读", "是", "一", "种", "技", "能。"])]
+    #[case("日本語を読む。カタカナ", vec!["日", "本", "語を", "読む。", "カタカナ"])]
+    fn it_splits_words(#[case] line: &str, #[case] expected: Vec<&str>) {
+        let words: Vec<&str> = word_ranges(line).into_iter().map(|r| &line[r]).collect();
+
+        check!(words == expected);
+    }
+}
@@ -0,0 +1,212 @@
+/// Height of the glyphs in pixels. Half blocks fit two pixels in a line of the terminal.
+pub const HEIGHT: usize = 5;
+
+/// Capital Latin-1 and Cyrillic letters, digits and common punctuation, `#` being a pixel set.
+const FONT: &[(char, [&str; HEIGHT])] = &[
+    ('A', [".##.", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "###."]),
+    ('C', [".###", "#...", "#...", "#...", ".###"]),
+    ('D', ["###.", "#..#", "#..#", "#..#", "###."]),
+    ('E', ["####", "#...", "###.", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#..."]),
+    ('G', [".###", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "####"]),
+    ('M', ["#...#", "##.##", "#.#.#", "#...#", "#...#"]),
+    ('N', ["#..#", "##.#", "#.##", "#..#", "#..#"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "###.", "#...", "#..."]),
+    ('Q', [".##.", "#..#", "#..#", "#.#.", ".#.#"]),
+    ('R', ["###.", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", ".##.", "...#", "###."]),
+    ('T', ["#####", "..#..", "..#..", "..#..", "..#.."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('V', ["#...#", "#...#", "#...#", ".#.#.", "..#.."]),
+    ('W', ["#...#", "#...#", "#.#.#", "##.##", "#...#"]),
+    ('X', ["#...#", ".#.#.", "..#..", ".#.#.", "#...#"]),
+    ('Y', ["#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", ".##.", "#...", "####"]),
+    ('0', [".##.", "#.##", "##.#", "#..#", ".##."]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["###.", "...#", ".##.", "#...", "####"]),
+    ('3', ["###.", "...#", ".##.", "...#", "###."]),
+    ('4', ["#..#", "#..#", "####", "...#", "...#"]),
+    ('5', ["####", "#...", "###.", "...#", "###."]),
+    ('6', [".##.", "#...", "###.", "#..#", ".##."]),
+    ('7', ["####", "...#", "..#.", ".#..", ".#.."]),
+    ('8', [".##.", "#..#", ".##.", "#..#", ".##."]),
+    ('9', [".##.", "#..#", ".###", "...#", ".##."]),
+    (' ', ["..", "..", "..", "..", ".."]),
+    ('.', [".", ".", ".", ".", "#"]),
+    (',', ["..", "..", "..", ".#", "#."]),
+    (';', ["..", ".#", "..", ".#", "#."]),
+    (':', [".", "#", ".", "#", "."]),
+    ('!', ["#", "#", "#", ".", "#"]),
+    ('?', ["###.", "...#", ".##.", "....", ".#.."]),
+    ('\'', ["#", "#", ".", ".", "."]),
+    ('"', ["#.#", "#.#", "...", "...", "..."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('(', [".#", "#.", "#.", "#.", ".#"]),
+    (')', ["#.", ".#", ".#", ".#", "#."]),
+    ('/', ["..#", "..#", ".#.", "#..", "#.."]),
+    ('&', [".#..", "#.#.", ".#..", "#.#.", ".#.#"]),
+    ('…', [".....", ".....", ".....", ".....", "#.#.#"]),
+    // Latin-1, accents taking the top row.
+    ('À', [".#..", ".##.", "#..#", "####", "#..#"]),
+    ('Á', ["..#.", ".##.", "#..#", "####", "#..#"]),
+    ('Â', [".##.", ".##.", "#..#", "####", "#..#"]),
+    ('Ã', ["####", ".##.", "#..#", "####", "#..#"]),
+    ('Ä', ["#..#", ".##.", "#..#", "####", "#..#"]),
+    ('Å', [".##.", ".##.", "#..#", "####", "#..#"]),
+    ('Æ', [".####", "#.#..", "####.", "#.#..", "#.###"]),
+    ('Ç', [".###", "#...", "#...", ".###", "..#."]),
+    ('È', [".#..", "####", "###.", "#...", "####"]),
+    ('É', ["..#.", "####", "###.", "#...", "####"]),
+    ('Ê', [".##.", "####", "###.", "#...", "####"]),
+    ('Ë', ["#..#", "####", "###.", "#...", "####"]),
+    ('Ì', ["#..", "###", ".#.", ".#.", "###"]),
+    ('Í', ["..#", "###", ".#.", ".#.", "###"]),
+    ('Î', [".#.", "###", ".#.", ".#.", "###"]),
+    ('Ï', ["#.#", "###", ".#.", ".#.", "###"]),
+    ('Ð', [".###.", ".#..#", "###.#", ".#..#", ".###."]),
+    ('Ñ', ["####", "#..#", "##.#", "#.##", "#..#"]),
+    ('Ò', [".#..", ".##.", "#..#", "#..#", ".##."]),
+    ('Ó', ["..#.", ".##.", "#..#", "#..#", ".##."]),
+    ('Ô', [".##.", ".##.", "#..#", "#..#", ".##."]),
+    ('Õ', ["####", ".##.", "#..#", "#..#", ".##."]),
+    ('Ö', ["#..#", ".##.", "#..#", "#..#", ".##."]),
+    ('Ø', [".###.", "#..##", "#.#.#", "##..#", ".###."]),
+    ('Ù', [".#..", "#..#", "#..#", "#..#", ".##."]),
+    ('Ú', ["..#.", "#..#", "#..#", "#..#", ".##."]),
+    ('Û', [".##.", "#..#", "#..#", "#..#", ".##."]),
+    ('Ü', ["#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Ý', ["...#.", "#...#", ".#.#.", "..#..", "..#.."]),
+    ('Þ', ["#...", "###.", "#..#", "###.", "#..."]),
+    ('ß', [".##.", "#..#", "#.#.", "#..#", "#.#."]),
+    ('Ÿ', [".#.#.", "#...#", ".#.#.", "..#..", "..#.."]),
+    ('¡', ["#", ".", "#", "#", "#"]),
+    ('¿', ["..#.", "....", ".##.", "#...", ".###"]),
+    // Cyrillic letters not drawn like Latin ones.
+    ('Б', ["####", "#...", "###.", "#..#", "###."]),
+    ('Г', ["####", "#...", "#...", "#...", "#..."]),
+    ('Ґ', ["...#", "####", "#...", "#...", "#..."]),
+    ('Д', [".###.", ".#.#.", ".#.#.", "#####", "#...#"]),
+    ('Ё', ["#..#", "####", "###.", "#...", "####"]),
+    ('Є', [".###", "#...", "###.", "#...", ".###"]),
+    ('Ж', ["#.#.#", "#.#.#", ".###.", "#.#.#", "#.#.#"]),
+    ('И', ["#..#", "#.##", "##.#", "#..#", "#..#"]),
+    ('Й', [".##.", "#..#", "#.##", "##.#", "#..#"]),
+    ('Л', [".###", ".#.#", ".#.#", ".#.#", "##.#"]),
+    ('П', ["####", "#..#", "#..#", "#..#", "#..#"]),
+    ('У', ["#..#", "#..#", ".###", "...#", "###."]),
+    ('Ф', [".###.", "#.#.#", "#.#.#", ".###.", "..#.."]),
+    ('Ц', ["#..#.", "#..#.", "#..#.", "####.", "....#"]),
+    ('Ч', ["#..#", "#..#", ".###", "...#", "...#"]),
+    ('Ш', ["#.#.#", "#.#.#", "#.#.#", "#.#.#", "#####"]),
+    ('Щ', ["#.#.#.", "#.#.#.", "#.#.#.", "######", ".....#"]),
+    ('Ъ', ["##..", ".#..", ".###", ".#.#", ".###"]),
+    ('Ы', ["#...#", "#...#", "###.#", "#.#.#", "###.#"]),
+    ('Ь', ["#...", "#...", "###.", "#..#", "###."]),
+    ('Э', ["###.", "...#", ".###", "...#", "###."]),
+    ('Ю', ["#..#.", "#.#.#", "###.#", "#.#.#", "#..#."]),
+    ('Я', [".###", "#..#", ".###", ".#.#", "#..#"]),
+];
+
+/// The glyph of a character, small letters being drawn as capital ones.
+fn glyph(c: char) -> Option<&'static [&'static str; HEIGHT]> {
+    // Letters such as `ß` have no single capital one.
+    let mut upper = c.to_uppercase();
+    let c = match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => c,
+    };
+    let c = match c {
+        '‘' | '’' => '\'',
+        '“' | '”' | '«' | '»' => '"',
+        '–' | '—' => '-',
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'З' => '3',
+        'І' => 'I',
+        'Ї' => 'Ï',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Т' => 'T',
+        'Х' => 'X',
+        c => c,
+    };
+    FONT.iter().find(|(g, _)| *g == c).map(|(_, rows)| rows)
+}
+
+/// Columns taken by the text drawn at the given scale, spacing included, or nothing if
+/// some of its characters have no glyph.
+pub fn width(text: &str, scale: usize) -> Option<usize> {
+    text.chars()
+        .map(|c| glyph(c).map(|rows| (rows[0].len() + 1) * scale))
+        .sum()
+}
+
+/// Lines of the text drawn at the given scale, a pixel being `scale` columns wide and
+/// `scale` half lines high.
+pub fn lines(text: &str, scale: usize) -> Vec<String> {
+    let glyphs: Vec<_> = text.chars().filter_map(glyph).collect();
+    let pixel = |row: usize, glyph: &[&str; HEIGHT], column: usize| {
+        glyph
+            .get(row / scale)
+            .and_then(|r| r.as_bytes().get(column / scale))
+            == Some(&b'#')
+    };
+    (0..(HEIGHT * scale).div_ceil(2))
+        .map(|line| {
+            let mut drawn = String::new();
+            for glyph in &glyphs {
+                for column in 0..(glyph[0].len() + 1) * scale {
+                    drawn.push(
+                        match (
+                            pixel(line * 2, glyph, column),
+                            pixel(line * 2 + 1, glyph, column),
+                        ) {
+                            (true, true) => '█',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (false, false) => ' ',
+                        },
+                    );
+                }
+            }
+            drawn
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_draws_glyphs() {
+        check!(lines("Hi", 1) == vec!["█  █ ▀█▀ ", "█▀▀█  █  ", "▀  ▀ ▀▀▀ "]);
+        check!(lines("i", 2) == vec!["██████  ", "  ██    ", "  ██    ", "  ██    ", "██████  "]);
+        check!(width("Hi", 3) == Some(27));
+    }
+
+    #[rstest]
+    #[case("“Don’t—”", true)]
+    #[case("naïve", true)]
+    #[case("Съешь ещё", true)]
+    #[case("Straße", true)]
+    #[case("日本", false)]
+    fn it_knows_which_characters_it_draws(#[case] text: &str, #[case] drawn: bool) {
+        check!(width(text, 1).is_some() == drawn);
+    }
+}
@@ -0,0 +1,89 @@
+/// Number of positions remembered.
+const MAX_JUMPS: usize = 100;
+
+/// A `(section_index, word_index)` position in a document.
+pub type Position = (usize, usize);
+
+/// The positions jumped away from, to walk back and forth through like Vim's jumplist.
+#[derive(Debug, Default)]
+pub struct Jumplist {
+    positions: Vec<Position>,
+    /// Position in the list the reader is at, the end of the list when not walking it.
+    current: usize,
+}
+
+impl Jumplist {
+    /// Remembers the position a jump leaves from. Positions ahead of the current one are
+    /// forgotten.
+    pub fn push(&mut self, from: Position) {
+        self.positions.truncate(self.current);
+        if self.positions.last() != Some(&from) {
+            self.positions.push(from);
+        }
+        if self.positions.len() > MAX_JUMPS {
+            self.positions.remove(0);
+        }
+        self.current = self.positions.len();
+    }
+
+    /// The position before the current one, `here` being where the reader is now.
+    pub fn back(&mut self, here: Position) -> Option<Position> {
+        if self.positions.is_empty() {
+            return None;
+        }
+        if self.current == self.positions.len() {
+            // Keep where the reader was to come back to it.
+            self.push(here);
+            self.current = self.positions.len() - 1;
+        }
+        if self.current == 0 {
+            return None;
+        }
+        self.current -= 1;
+        Some(self.positions[self.current])
+    }
+
+    /// The position after the current one, when walking back through the list.
+    pub fn forward(&mut self) -> Option<Position> {
+        if self.current + 1 >= self.positions.len() {
+            return None;
+        }
+        self.current += 1;
+        Some(self.positions[self.current])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_walks_back_and_forth() {
+        let mut jumps = Jumplist::default();
+        check!(jumps.back((0, 0)) == None);
+
+        jumps.push((0, 10));
+        jumps.push((3, 0));
+        check!(jumps.back((5, 7)) == Some((3, 0)));
+        check!(jumps.back((3, 0)) == Some((0, 10)));
+        check!(jumps.back((0, 10)) == None);
+        check!(jumps.forward() == Some((3, 0)));
+        check!(jumps.forward() == Some((5, 7)));
+        check!(jumps.forward() == None);
+    }
+
+    #[rstest]
+    fn it_forgets_positions_ahead_on_a_new_jump() {
+        let mut jumps = Jumplist::default();
+        jumps.push((0, 10));
+        jumps.push((3, 0));
+        check!(jumps.back((5, 7)) == Some((3, 0)));
+
+        jumps.push((3, 0));
+        check!(jumps.forward() == None);
+        check!(jumps.back((8, 1)) == Some((3, 0)));
+        check!(jumps.back((3, 0)) == Some((0, 10)));
+    }
+}
@@ -0,0 +1,461 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something a key can be bound to while reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    ToggleStatus,
+    PrevWord,
+    NextWord,
+    PrevLine,
+    NextLine,
+    PrevSentence,
+    NextSentence,
+    PrevParagraph,
+    NextParagraph,
+    Rewind,
+    PrevSection,
+    NextSection,
+    IncreaseSpeed,
+    DecreaseSpeed,
+    Chunk1,
+    Chunk2,
+    Chunk3,
+    Chunk4,
+    Chunk5,
+    TocUp,
+    TocDown,
+    TocLeft,
+    TocRight,
+    TocSelect,
+    Search,
+    NextHit,
+    PrevHit,
+    CloseSearch,
+    Bookmark,
+    Bookmarks,
+    Highlight,
+    JumpBack,
+    JumpForward,
+    LargeGlyphs,
+    Help,
+    BookmarksUp,
+    BookmarksDown,
+    BookmarksJump,
+    BookmarksRename,
+    BookmarksDelete,
+    BookmarksClose,
+    PromptSubmit,
+    PromptCancel,
+    PromptDeleteChar,
+    LibraryUp,
+    LibraryDown,
+    LibraryOpen,
+    LibrarySort,
+    LibraryFilter,
+    LibraryQuit,
+}
+
+/// Where keys are read: actions of different modes can share keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Reading,
+    /// The bookmarks pane, whose keys come before the reading ones while it is open.
+    Bookmarks,
+    /// A line of text being typed, where the keys without an action are typed.
+    Prompt,
+    Library,
+}
+
+impl Action {
+    pub fn mode(self) -> Mode {
+        match self {
+            Action::BookmarksUp
+            | Action::BookmarksDown
+            | Action::BookmarksJump
+            | Action::BookmarksRename
+            | Action::BookmarksDelete
+            | Action::BookmarksClose => Mode::Bookmarks,
+            Action::PromptSubmit | Action::PromptCancel | Action::PromptDeleteChar => Mode::Prompt,
+            Action::LibraryUp
+            | Action::LibraryDown
+            | Action::LibraryOpen
+            | Action::LibrarySort
+            | Action::LibraryFilter
+            | Action::LibraryQuit => Mode::Library,
+            _ => Mode::Reading,
+        }
+    }
+}
+
+/// Every action with its description and default keys, in the order the help lists them.
+const ACTIONS: &[(Action, &str, &[&str])] = &[
+    (Action::Quit, "Quit", &["q"]),
+    (Action::ToggleStatus, "Start or pause reading", &["space"]),
+    (Action::PrevWord, "Previous word", &["left"]),
+    (Action::NextWord, "Next word", &["right"]),
+    (Action::PrevLine, "Previous line", &["up"]),
+    (Action::NextLine, "Next line", &["down"]),
+    (Action::PrevSentence, "Previous sentence", &["("]),
+    (Action::NextSentence, "Next sentence", &[")"]),
+    (Action::PrevParagraph, "Previous paragraph", &["{"]),
+    (Action::NextParagraph, "Next paragraph", &["}"]),
+    (Action::Rewind, "Rewind ten seconds", &["backspace"]),
+    (Action::PrevSection, "Previous section", &["pageup"]),
+    (Action::NextSection, "Next section", &["pagedown"]),
+    (Action::IncreaseSpeed, "Read faster", &["+"]),
+    (Action::DecreaseSpeed, "Read slower", &["-"]),
+    (Action::Chunk1, "Show one word at once", &["1"]),
+    (Action::Chunk2, "Show two words at once", &["2"]),
+    (Action::Chunk3, "Show three words at once", &["3"]),
+    (Action::Chunk4, "Show four words at once", &["4"]),
+    (Action::Chunk5, "Show five words at once", &["5"]),
+    (Action::TocUp, "Table of contents: up", &["w"]),
+    (Action::TocDown, "Table of contents: down", &["s"]),
+    (Action::TocLeft, "Table of contents: collapse", &["a"]),
+    (Action::TocRight, "Table of contents: expand", &["d"]),
+    (Action::TocSelect, "Table of contents: open", &["enter"]),
+    (Action::Search, "Search", &["/"]),
+    (Action::NextHit, "Next search hit", &["n"]),
+    (Action::PrevHit, "Previous search hit", &["N"]),
+    (Action::CloseSearch, "Close the search results", &["esc"]),
+    (Action::Bookmark, "Add a bookmark", &["m"]),
+    (Action::Bookmarks, "Show or hide the bookmarks", &["b"]),
+    (Action::Highlight, "Start or end a highlight", &["v"]),
+    (Action::JumpBack, "Jump back", &["ctrl-o"]),
+    // Terminals send Ctrl-I as a tab.
+    (Action::JumpForward, "Jump forward", &["ctrl-i", "tab"]),
+    (
+        Action::LargeGlyphs,
+        "Show the word in large glyphs or not",
+        &["g"],
+    ),
+    (Action::Help, "Show or hide this help", &["?"]),
+    (Action::BookmarksUp, "Bookmarks: up", &["up"]),
+    (Action::BookmarksDown, "Bookmarks: down", &["down"]),
+    (Action::BookmarksJump, "Bookmarks: jump", &["enter"]),
+    (Action::BookmarksRename, "Bookmarks: rename", &["r"]),
+    (
+        Action::BookmarksDelete,
+        "Bookmarks: delete",
+        &["x", "delete"],
+    ),
+    (Action::BookmarksClose, "Bookmarks: close", &["esc"]),
+    (Action::PromptSubmit, "Prompt: submit", &["enter"]),
+    (Action::PromptCancel, "Prompt: cancel", &["esc"]),
+    (
+        Action::PromptDeleteChar,
+        "Prompt: delete a character",
+        &["backspace"],
+    ),
+    (Action::LibraryUp, "Library: up", &["up"]),
+    (Action::LibraryDown, "Library: down", &["down"]),
+    (Action::LibraryOpen, "Library: open", &["enter"]),
+    (Action::LibrarySort, "Library: sort", &["s"]),
+    (Action::LibraryFilter, "Library: filter", &["/"]),
+    (Action::LibraryQuit, "Library: quit", &["q", "esc"]),
+];
+
+/// A key with its modifiers, written like `ctrl-o`, `shift-tab`, `pagedown` or `N`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("space", KeyCode::Char(' ')),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+];
+
+impl Key {
+    /// The key with the modifiers that matter: shift is part of the character typed, so
+    /// `shift-n` is `N`.
+    fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::BackTab => {
+                modifiers.insert(KeyModifiers::SHIFT);
+                KeyCode::Tab
+            }
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                let mut upper = c.to_uppercase();
+                match (upper.next(), upper.next()) {
+                    (Some(upper), None) => KeyCode::Char(upper),
+                    _ => KeyCode::Char(c),
+                }
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        // A single character is a key even when it is a dash.
+        while rest.chars().count() > 1 {
+            let Some((modifier, key)) = rest.split_once('-') else {
+                break;
+            };
+            modifiers.insert(match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier `{modifier}` in key `{s}`"),
+            });
+            rest = key;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let name = rest.to_lowercase();
+                match KEY_NAMES.iter().find(|(n, _)| *n == name) {
+                    Some((_, code)) => *code,
+                    None => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n @ 1..=12) => KeyCode::F(n),
+                        _ => bail!("unknown key `{s}`"),
+                    },
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl-"),
+            (KeyModifiers::ALT, "alt-"),
+            (KeyModifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match KEY_NAMES.iter().find(|(_, code)| *code == self.code) {
+            Some((name, _)) => f.write_str(name),
+            None => match self.code {
+                KeyCode::Char(c) => write!(f, "{c}"),
+                KeyCode::F(n) => write!(f, "f{n}"),
+                code => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+/// The keys bound to every action.
+pub struct Keymap {
+    bindings: HashMap<(Mode, Key), Action>,
+    /// Keys of every action, in the order they were given.
+    keys: HashMap<Action, Vec<Key>>,
+}
+
+impl Keymap {
+    /// The default bindings, where the keys given for an action replace its default ones.
+    /// A key can only be bound to one action of a mode, so overrides that bind a key twice
+    /// or take the default key of another action are refused.
+    pub fn new(overrides: &HashMap<String, Vec<String>>) -> anyhow::Result<Self> {
+        let mut keys = HashMap::new();
+        let mut overrides: Vec<_> = overrides.iter().collect();
+        overrides.sort();
+        for (action, action_keys) in overrides {
+            let action: Action = action
+                .parse()
+                .map_err(|_| anyhow!("unknown action `{action}`"))?;
+            let action_keys = action_keys
+                .iter()
+                .map(|k| k.parse())
+                .collect::<anyhow::Result<Vec<Key>>>()?;
+            keys.insert(action, action_keys);
+        }
+        for (action, _, default_keys) in ACTIONS {
+            if !keys.contains_key(action) {
+                let default_keys = default_keys
+                    .iter()
+                    .map(|k| k.parse())
+                    .collect::<anyhow::Result<Vec<Key>>>()?;
+                keys.insert(*action, default_keys);
+            }
+        }
+
+        let mut bindings = HashMap::new();
+        for (action, _, _) in ACTIONS {
+            for key in &keys[action] {
+                if let Some(other) = bindings.insert((action.mode(), *key), *action) {
+                    if other != *action {
+                        bail!("`{key}` is bound to both `{other}` and `{action}`");
+                    }
+                }
+            }
+        }
+        Ok(Self { bindings, keys })
+    }
+
+    pub fn action(&self, mode: Mode, event: KeyEvent) -> Option<Action> {
+        self.bindings.get(&(mode, Key::from(event))).copied()
+    }
+
+    /// The keys bound to an action, as written in the configuration.
+    pub fn keys(&self, action: Action) -> Vec<String> {
+        self.keys[&action].iter().map(Key::to_string).collect()
+    }
+
+    /// A line telling the first key of each action, like `enter: open, q: quit`. Actions
+    /// without keys are left out.
+    pub fn hint(&self, actions: &[(Action, &str)]) -> String {
+        actions
+            .iter()
+            .filter_map(|(action, name)| Some(format!("{}: {name}", self.keys[action].first()?)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Every action with its description and the keys bound to it.
+    pub fn help(&self) -> Vec<(Vec<String>, &'static str)> {
+        ACTIONS
+            .iter()
+            .map(|(action, description, _)| (self.keys(*action), *description))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("q", KeyCode::Char('q'), KeyModifiers::NONE)]
+    #[case("N", KeyCode::Char('N'), KeyModifiers::NONE)]
+    #[case("-", KeyCode::Char('-'), KeyModifiers::NONE)]
+    #[case("ctrl--", KeyCode::Char('-'), KeyModifiers::CONTROL)]
+    #[case("Ctrl-Alt-o", KeyCode::Char('o'), KeyModifiers::CONTROL | KeyModifiers::ALT)]
+    #[case("shift-tab", KeyCode::Tab, KeyModifiers::SHIFT)]
+    #[case("PageDown", KeyCode::PageDown, KeyModifiers::NONE)]
+    #[case("f5", KeyCode::F(5), KeyModifiers::NONE)]
+    #[case("shift-n", KeyCode::Char('N'), KeyModifiers::NONE)]
+    #[case("ctrl-shift-n", KeyCode::Char('N'), KeyModifiers::CONTROL)]
+    fn it_parses_keys(#[case] key: &str, #[case] code: KeyCode, #[case] modifiers: KeyModifiers) {
+        let_assert!(Ok(parsed) = key.parse::<Key>());
+        check!(parsed == Key { code, modifiers });
+    }
+
+    #[rstest]
+    #[case("hyper-x")]
+    #[case("pgdn")]
+    fn it_rejects_unknown_keys(#[case] key: &str) {
+        check!(key.parse::<Key>().is_err());
+    }
+
+    #[rstest]
+    fn it_replaces_default_keys() {
+        let overrides = HashMap::from([
+            ("toc-up".to_string(), vec!["k".to_string()]),
+            (
+                "toc-down".to_string(),
+                vec!["j".to_string(), "ctrl-n".to_string()],
+            ),
+        ]);
+        let keymap = Keymap::new(&overrides).unwrap();
+
+        let event = |code, modifiers| KeyEvent::new(code, modifiers);
+        check!(
+            keymap.action(Mode::Reading, event(KeyCode::Char('k'), KeyModifiers::NONE))
+                == Some(Action::TocUp)
+        );
+        check!(keymap.action(Mode::Reading, event(KeyCode::Char('w'), KeyModifiers::NONE)) == None);
+        check!(
+            keymap.action(
+                Mode::Reading,
+                event(KeyCode::Char('n'), KeyModifiers::CONTROL)
+            ) == Some(Action::TocDown)
+        );
+        check!(
+            keymap.action(
+                Mode::Reading,
+                event(KeyCode::Char('N'), KeyModifiers::SHIFT)
+            ) == Some(Action::PrevHit)
+        );
+        check!(keymap.action(Mode::Reading, event(KeyCode::BackTab, KeyModifiers::SHIFT)) == None);
+        let help = keymap.help();
+        check!(help.contains(&(
+            vec!["j".to_string(), "ctrl-n".to_string()],
+            "Table of contents: down"
+        )));
+    }
+
+    #[rstest]
+    fn it_keeps_modes_apart() {
+        let keymap = Keymap::new(&HashMap::new()).unwrap();
+
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        check!(keymap.action(Mode::Reading, enter) == Some(Action::TocSelect));
+        check!(keymap.action(Mode::Bookmarks, enter) == Some(Action::BookmarksJump));
+        check!(keymap.action(Mode::Library, enter) == Some(Action::LibraryOpen));
+        check!(
+            keymap.hint(&[
+                (Action::BookmarksJump, "jump"),
+                (Action::BookmarksDelete, "delete")
+            ]) == "enter: jump, x: delete"
+        );
+    }
+
+    #[rstest]
+    #[case(&[("toc-up", "k"), ("toc-down", "k")])]
+    #[case(&[("toc-down", "n")])]
+    fn it_rejects_keys_bound_twice(#[case] overrides: &[(&str, &str)]) {
+        let overrides = overrides
+            .iter()
+            .map(|(action, key)| (action.to_string(), vec![key.to_string()]))
+            .collect();
+
+        let_assert!(Err(error) = Keymap::new(&overrides));
+        check!(error.to_string().contains("is bound to both"));
+    }
+
+    #[rstest]
+    fn it_allows_moving_a_default_key() {
+        let overrides = HashMap::from([
+            ("toc-down".to_string(), vec!["n".to_string()]),
+            ("next-hit".to_string(), vec!["ctrl-n".to_string()]),
+        ]);
+        check!(Keymap::new(&overrides).is_ok());
+    }
+
+    #[rstest]
+    fn it_rejects_unknown_actions() {
+        let overrides = HashMap::from([("fly".to_string(), vec!["f".to_string()])]);
+        check!(Keymap::new(&overrides).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::state::store_json;
+
+/// A document that was read, as listed in the library.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub last_opened: SystemTime,
+    /// Fraction of the document read.
+    pub progress: f64,
+    /// Number of words left to read, counted when the document was last closed.
+    #[serde(default)]
+    pub words_left: Option<usize>,
+}
+
+/// Every document read, by the identifier its state is stored under.
+#[derive(Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Library {
+    books: BTreeMap<String, LibraryEntry>,
+}
+
+impl Library {
+    pub fn load(state_dir: &Path) -> Self {
+        std::fs::read_to_string(Self::path(state_dir))
+            .ok()
+            .and_then(|l| serde_json::from_str(&l).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn store(&self, state_dir: &Path) -> anyhow::Result<()> {
+        store_json(&Self::path(state_dir), self)
+    }
+
+    fn path(state_dir: &Path) -> PathBuf {
+        state_dir.join("library.json")
+    }
+
+    pub fn books(&self) -> impl Iterator<Item = (&String, &LibraryEntry)> {
+        self.books.iter()
+    }
+
+    pub fn update(&mut self, identifier: String, entry: LibraryEntry) {
+        self.books.insert(identifier, entry);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_stores_the_library() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = Library::load(dir);
+        check!(library == Library::default());
+
+        library.update("book".to_string(), entry("book.epub", 0.1));
+        library.update("paper".to_string(), entry("paper.pdf", 0.0));
+        library.update("book".to_string(), entry("book.epub", 0.5));
+        library.store(dir).unwrap();
+
+        let library = Library::load(dir);
+        check!(library.books.len() == 2);
+        check!(library.books["book"] == entry("book.epub", 0.5));
+    }
+
+    fn entry(path: &str, progress: f64) -> LibraryEntry {
+        LibraryEntry {
+            path: PathBuf::from(path),
+            title: None,
+            author: None,
+            last_opened: SystemTime::UNIX_EPOCH,
+            progress,
+            words_left: None,
+        }
+    }
+}
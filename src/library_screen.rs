@@ -0,0 +1,329 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use chrono::{DateTime, Local};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    widgets::{Block, Borders, Paragraph, Row, Table, TableState},
+    Frame, Terminal,
+};
+
+use crate::{
+    document::DocState,
+    keymap::{Action, Keymap, Mode},
+    library::{Library, LibraryEntry},
+    theme::Theme,
+    DEFAULT_WPM,
+};
+
+/// A document of the library, with the speed it is read at.
+struct Book {
+    entry: LibraryEntry,
+    wpm: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, strum::Display)]
+enum Sort {
+    #[strum(to_string = "last read")]
+    LastRead,
+    #[strum(to_string = "title")]
+    Title,
+    #[strum(to_string = "progress")]
+    Progress,
+}
+
+struct LibraryModel {
+    books: Vec<Book>,
+    sort: Sort,
+    filter: String,
+    /// Whether keys go to the filter.
+    filtering: bool,
+    /// Positions in `books` of the books shown, in order.
+    visible: Vec<usize>,
+    state: TableState,
+    chosen: Option<PathBuf>,
+    should_quit: bool,
+}
+
+#[derive(PartialEq)]
+enum LibraryMessage {
+    Up,
+    Down,
+    Open,
+    Quit,
+    NextSort,
+    StartFilter,
+    FilterInput(char),
+    FilterDeleteChar,
+    EndFilter,
+}
+
+/// Lets the user pick a document read before, returning its path, or nothing if they
+/// quit instead.
+pub fn run(state_dir: &Path, theme: &Theme, keymap: &Keymap) -> anyhow::Result<Option<PathBuf>> {
+    let books: Vec<Book> = Library::load(state_dir)
+        .books()
+        .filter(|(_, entry)| entry.path.exists())
+        .map(|(identifier, entry)| Book {
+            entry: entry.clone(),
+            wpm: DocState::load(state_dir, identifier.clone())
+                .wpm
+                .unwrap_or(DEFAULT_WPM),
+        })
+        .collect();
+    if books.is_empty() {
+        bail!("no document given, and none was read before");
+    }
+    let mut model = LibraryModel {
+        books,
+        sort: Sort::LastRead,
+        filter: String::new(),
+        filtering: false,
+        visible: vec![],
+        state: TableState::default(),
+        chosen: None,
+        should_quit: false,
+    };
+    refresh(&mut model);
+
+    crossterm::terminal::enable_raw_mode()?;
+    crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
+    while !model.should_quit {
+        terminal.draw(|f| view(&mut model, theme, keymap, f))?;
+        if let Some(msg) = handle_event(&model, keymap)? {
+            update(&mut model, msg);
+        }
+    }
+    crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
+    crossterm::terminal::disable_raw_mode()?;
+    Ok(model.chosen)
+}
+
+fn update(model: &mut LibraryModel, msg: LibraryMessage) {
+    let selected = model.state.selected();
+    match msg {
+        LibraryMessage::Up => model.state.select(selected.map(|s| s.saturating_sub(1))),
+        LibraryMessage::Down => model
+            .state
+            .select(selected.map(|s| (s + 1).min(model.visible.len().saturating_sub(1)))),
+        LibraryMessage::Open => {
+            if let Some(book) = selected.and_then(|s| model.visible.get(s)) {
+                model.chosen = Some(model.books[*book].entry.path.clone());
+                model.should_quit = true;
+            }
+        }
+        LibraryMessage::Quit => model.should_quit = true,
+        LibraryMessage::NextSort => {
+            model.sort = match model.sort {
+                Sort::LastRead => Sort::Title,
+                Sort::Title => Sort::Progress,
+                Sort::Progress => Sort::LastRead,
+            };
+            refresh(model);
+        }
+        LibraryMessage::StartFilter => model.filtering = true,
+        LibraryMessage::FilterInput(c) => {
+            model.filter.push(c);
+            refresh(model);
+        }
+        LibraryMessage::FilterDeleteChar => {
+            model.filter.pop();
+            refresh(model);
+        }
+        LibraryMessage::EndFilter => model.filtering = false,
+    }
+}
+
+/// Sorts and filters the books again, selecting the first one.
+fn refresh(model: &mut LibraryModel) {
+    model.visible = visible(&model.books, model.sort, &model.filter);
+    model.state.select((!model.visible.is_empty()).then_some(0));
+}
+
+/// Positions of the books whose title, author or path contain the filter, sorted.
+fn visible(books: &[Book], sort: Sort, filter: &str) -> Vec<usize> {
+    let filter = filter.to_lowercase();
+    let mut visible: Vec<usize> = (0..books.len())
+        .filter(|i| {
+            let entry = &books[*i].entry;
+            [
+                entry.title.clone(),
+                entry.author.clone(),
+                Some(entry.path.to_string_lossy().to_string()),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|text| text.to_lowercase().contains(&filter))
+        })
+        .collect();
+    match sort {
+        Sort::LastRead => visible.sort_by_key(|i| std::cmp::Reverse(books[*i].entry.last_opened)),
+        Sort::Title => visible.sort_by_key(|i| title(&books[*i].entry).to_lowercase()),
+        Sort::Progress => visible.sort_by(|a, b| {
+            books[*b]
+                .entry
+                .progress
+                .total_cmp(&books[*a].entry.progress)
+        }),
+    }
+    visible
+}
+
+fn title(entry: &LibraryEntry) -> String {
+    entry.title.clone().unwrap_or_else(|| {
+        entry
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    })
+}
+
+/// Reading time left at the given speed, like `1h 05m`.
+fn time_left(words: usize, wpm: f64) -> String {
+    let minutes = (words as f64 / wpm).ceil() as u64;
+    if minutes < 60 {
+        format!("{minutes}m")
+    } else {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+fn view(model: &mut LibraryModel, theme: &Theme, keymap: &Keymap, f: &mut Frame) {
+    f.render_widget(Block::default().style(theme.text), f.size());
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Min(3), Constraint::Max(3)].as_ref())
+        .split(f.size());
+    let rows: Vec<Row> = model
+        .visible
+        .iter()
+        .map(|i| {
+            let book = &model.books[*i];
+            let entry = &book.entry;
+            Row::new(vec![
+                title(entry),
+                entry.author.clone().unwrap_or_default(),
+                format!("{:.0}%", entry.progress * 100.0),
+                DateTime::<Local>::from(entry.last_opened)
+                    .format("%Y-%m-%d")
+                    .to_string(),
+                entry
+                    .words_left
+                    .map_or("-".to_string(), |words| time_left(words, book.wpm)),
+            ])
+        })
+        .collect();
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["Title", "Author", "Read", "Last read", "Time left"])
+                .style(theme.heading),
+        )
+        .widths(&[
+            Constraint::Percentage(45),
+            Constraint::Percentage(25),
+            Constraint::Length(5),
+            Constraint::Length(10),
+            Constraint::Length(9),
+        ])
+        .highlight_style(theme.selection)
+        .block(
+            Block::default()
+                .title(format!("Library, by {}", model.sort))
+                .borders(Borders::ALL),
+        );
+    f.render_stateful_widget(table, layout[0], &mut model.state);
+
+    let help = if model.filtering || !model.filter.is_empty() {
+        model.filter.clone()
+    } else {
+        keymap.hint(&[
+            (Action::LibraryOpen, "open"),
+            (Action::LibrarySort, "sort"),
+            (Action::LibraryFilter, "filter"),
+            (Action::LibraryQuit, "quit"),
+        ])
+    };
+    f.render_widget(
+        Paragraph::new(help).block(Block::default().title("Filter").borders(Borders::ALL)),
+        layout[1],
+    );
+}
+
+fn handle_event(model: &LibraryModel, keymap: &Keymap) -> anyhow::Result<Option<LibraryMessage>> {
+    let crossterm::event::Event::Key(key) = crossterm::event::read()? else {
+        return Ok(None);
+    };
+    if model.filtering {
+        let msg = match keymap.action(Mode::Prompt, key) {
+            Some(Action::PromptSubmit | Action::PromptCancel) => Some(LibraryMessage::EndFilter),
+            Some(Action::PromptDeleteChar) => Some(LibraryMessage::FilterDeleteChar),
+            _ => match key.code {
+                crossterm::event::KeyCode::Char(c) => Some(LibraryMessage::FilterInput(c)),
+                _ => None,
+            },
+        };
+        if msg.is_some() {
+            return Ok(msg);
+        }
+    }
+    Ok(match keymap.action(Mode::Library, key) {
+        Some(Action::LibraryUp) => Some(LibraryMessage::Up),
+        Some(Action::LibraryDown) => Some(LibraryMessage::Down),
+        // The list can still be moved through while filtering.
+        _ if model.filtering => None,
+        Some(Action::LibraryOpen) => Some(LibraryMessage::Open),
+        Some(Action::LibrarySort) => Some(LibraryMessage::NextSort),
+        Some(Action::LibraryFilter) => Some(LibraryMessage::StartFilter),
+        Some(Action::LibraryQuit) => Some(LibraryMessage::Quit),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+    use std::time::{Duration, SystemTime};
+
+    #[rstest]
+    fn it_sorts_and_filters_books() {
+        let books = vec![
+            book("Think Like a Freak", "Levitt", 0.2, 10),
+            book("Anna Karenina", "Tolstoy", 0.9, 5),
+            book("War and Peace", "Tolstoy", 0.5, 20),
+        ];
+
+        check!(visible(&books, Sort::LastRead, "") == vec![2, 0, 1]);
+        check!(visible(&books, Sort::Title, "") == vec![1, 0, 2]);
+        check!(visible(&books, Sort::Progress, "") == vec![1, 2, 0]);
+        check!(visible(&books, Sort::Title, "tolSTOY") == vec![1, 2]);
+    }
+
+    #[rstest]
+    #[case(3000, 300.0, "10m")]
+    #[case(30000, 250.0, "2h 00m")]
+    #[case(100, 300.0, "1m")]
+    fn it_shows_the_time_left(#[case] words: usize, #[case] wpm: f64, #[case] expected: &str) {
+        check!(time_left(words, wpm) == expected);
+    }
+
+    fn book(title: &str, author: &str, progress: f64, opened: u64) -> Book {
+        Book {
+            entry: LibraryEntry {
+                path: PathBuf::from(format!("{title}.epub")),
+                title: Some(title.to_string()),
+                author: Some(author.to_string()),
+                last_opened: SystemTime::UNIX_EPOCH + Duration::from_secs(opened),
+                progress,
+                words_left: None,
+            },
+            wpm: DEFAULT_WPM,
+        }
+    }
+}
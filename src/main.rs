@@ -1,37 +1,118 @@
 use std::{
     cmp::{max, min},
+    io::IsTerminal,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
-    time::{Duration, Instant},
-    u16,
+    time::{Duration, Instant, SystemTime},
 };
 
-use clap::{Parser, ValueHint};
-use document::{DocState, DocumentCursor, TableOfContentNode};
+use anyhow::{bail, Context};
+use bookmarks::{Bookmark, Bookmarks};
+use clap::{Parser, Subcommand, ValueHint};
+use document::{DocState, DocumentCursor, SearchHit, SectionCursor, TableOfContentNode};
+mod bookmarks;
+mod config;
 mod document;
-use itertools::Itertools;
+mod glyphs;
+mod jumplist;
+mod keymap;
+mod library;
+mod library_screen;
+mod notes;
+mod state;
+mod theme;
+mod timing;
+use jumplist::Jumplist;
+use keymap::{Action, Keymap, Mode};
+use library::{Library, LibraryEntry};
+use notes::{ExportFormat, Highlight, Notes};
 use ratatui::{
     backend::CrosstermBackend,
     layout::Layout,
-    layout::{Alignment, Constraint, Direction},
-    style::{Color, Style},
+    layout::{Constraint, Direction, Rect},
+    style::Style,
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Table},
     Frame, Terminal,
 };
-use strum;
+use theme::Theme;
+use timing::{Pace, Pause, Timing};
 use tui_tree_widget::{Tree, TreeItem, TreeState};
+use unicode_width::UnicodeWidthStr;
 
-const CONFIG_PATH: &'static str = ".config/";
+const DEFAULT_WPM: f64 = 300.0;
+const FASTEST: Duration = Duration::from_millis(50);
+const SLOWEST: Duration = Duration::from_secs(2);
+/// Reading time stepped back by a rewind.
+const REWIND: Duration = Duration::from_secs(10);
 
 #[derive(Parser)]
+#[clap(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[clap(subcommand)]
+    command: Option<Command>,
+    /// Document to read, `-` or nothing to read plain text from a pipe
     #[clap(value_hint = ValueHint::AnyPath)]
-    path: PathBuf,
-    #[clap(short, value_parser = parse_speed)]
-    speed: Duration,
+    path: Option<PathBuf>,
+    /// Delay between words in milliseconds, as an alternative to --wpm
+    #[clap(short, long, value_parser = parse_millis, conflicts_with_all = ["wpm", "target_minutes"])]
+    speed: Option<Duration>,
+    /// Words per minute, the last speed used for the document by default
+    #[clap(short, long, value_parser = |arg: &str| parse_positive(arg, "must be a positive number"), conflicts_with = "target_minutes")]
+    wpm: Option<f64>,
+    /// Picks the speed needed to finish the current section in that many minutes
+    #[clap(long, value_parser = |arg: &str| parse_positive(arg, "must be a positive number of minutes"))]
+    target_minutes: Option<f64>,
+    /// Extra time in milliseconds on the last word of a paragraph
+    #[clap(long, value_parser = parse_millis, default_value = "300")]
+    paragraph_pause: Duration,
+    /// Extra time in milliseconds on the last word of a section
+    #[clap(long, value_parser = parse_millis, default_value = "1000")]
+    section_pause: Duration,
+    /// Number of words over which reading speeds up after a pause or a new section
+    #[clap(long, default_value = "10")]
+    ramp_words: usize,
+    /// Speed in percent the ramp starts at
+    #[clap(long, default_value = "50", value_parser = clap::value_parser!(u8).range(1..=100))]
+    ramp_start: u8,
+    /// Number of words shown at once
+    #[clap(short, long, default_value = "1", value_parser = clap::value_parser!(u8).range(1..=5))]
+    chunk: u8,
+    /// Draws the current word in large glyphs, as big as the terminal allows. Words
+    /// with characters outside Latin-1 and Cyrillic, such as CJK, stay in small text
+    #[clap(long)]
+    large_glyphs: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the highlights and notes taken in a document
+    ExportNotes {
+        #[clap(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+        #[clap(long, value_enum, default_value = "md")]
+        format: ExportFormat,
+    },
+}
+
+fn parse_positive(arg: &str, error: &str) -> anyhow::Result<f64> {
+    let value: f64 = arg.parse()?;
+    if !(value.is_finite() && value > 0.0) {
+        bail!("{error}");
+    }
+    Ok(value)
+}
+
+fn wpm(speed: Duration) -> f64 {
+    60.0 / speed.as_secs_f64()
 }
 
-fn parse_speed(arg: &str) -> Result<std::time::Duration, std::num::ParseIntError> {
+/// Delay between words for the given words per minute, within the supported speeds.
+fn speed(wpm: f64) -> Duration {
+    Duration::try_from_secs_f64(60.0 / wpm).map_or(SLOWEST, |speed| speed.clamp(FASTEST, SLOWEST))
+}
+
+fn parse_millis(arg: &str) -> Result<std::time::Duration, std::num::ParseIntError> {
     let millis = arg.parse()?;
     Ok(std::time::Duration::from_millis(millis))
 }
@@ -44,14 +125,57 @@ enum Status {
 
 struct Model {
     should_quit: bool,
+    state_dir: PathBuf,
     cursor: DocumentCursor,
     table_of_contents: Vec<TreeItem<'static, usize>>,
     table_of_contents_state: TreeState<usize>,
     last_word_change: Instant,
     speed: Duration,
+    timing: Timing,
+    /// How long the current word is shown.
+    word_duration: Duration,
+    pace: Pace,
+    /// Words left before reading is back at full speed.
+    ramp_remaining: usize,
+    prompt: Option<Prompt>,
+    search: Search,
+    bookmarks: Bookmarks,
+    bookmarks_state: ListState,
+    show_bookmarks: bool,
+    notes: Notes,
+    /// Section and word a highlight being marked starts at.
+    mark: Option<(usize, usize)>,
+    jumplist: Jumplist,
+    keymap: Keymap,
+    show_help: bool,
+    /// Rows of the help scrolled past.
+    help_offset: usize,
+    large_glyphs: bool,
+    theme: Theme,
     status: Status,
 }
 
+/// A line of text being typed in the status bar.
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PromptKind {
+    Search,
+    BookmarkName,
+    RenameBookmark(usize),
+    Note,
+}
+
+#[derive(Default)]
+struct Search {
+    query: String,
+    hits: Vec<SearchHit>,
+    hits_state: ListState,
+}
+
 #[derive(PartialEq)]
 enum Message {
     Quit,
@@ -59,12 +183,33 @@ enum Message {
     NextWord,
     PrevLine,
     NextLine,
+    PrevSentence,
+    NextSentence,
+    PrevParagraph,
+    NextParagraph,
+    Rewind,
     PrevSection,
     NextSection,
     IncreaseSpeed,
     DecreaseSpeed,
     ToggleStatus,
+    SetChunkSize(usize),
+    JumpBack,
+    JumpForward,
+    ToggleLargeGlyphs,
+    HelpMessage(HelpMessage),
     TableOfContentsMessage(TableOfContentsMessage),
+    PromptMessage(PromptMessage),
+    SearchMessage(SearchMessage),
+    BookmarkMessage(BookmarkMessage),
+    HighlightMessage(HighlightMessage),
+}
+
+#[derive(PartialEq)]
+enum HelpMessage {
+    Toggle,
+    Up,
+    Down,
 }
 
 #[derive(PartialEq)]
@@ -76,11 +221,49 @@ enum TableOfContentsMessage {
     Up,
 }
 
+#[derive(PartialEq)]
+enum PromptMessage {
+    Input(char),
+    DeleteChar,
+    Submit,
+    Cancel,
+}
+
+#[derive(PartialEq)]
+enum SearchMessage {
+    Open,
+    Find(String),
+    Close,
+    Next,
+    Prev,
+}
+
+#[derive(PartialEq)]
+enum BookmarkMessage {
+    New,
+    Add(String),
+    TogglePane,
+    Up,
+    Down,
+    Jump,
+    StartRename,
+    Rename(usize, String),
+    Delete,
+}
+
+#[derive(PartialEq)]
+enum HighlightMessage {
+    Mark,
+    Add(String),
+}
+
 fn update(model: &mut Model, msg: Message) -> Option<Message> {
     match msg {
         Message::Quit => {
             model.should_quit = true;
-            let _ = model.cursor.doc_state().store(Path::new(CONFIG_PATH));
+            let mut doc_state = model.cursor.doc_state();
+            doc_state.wpm = Some(wpm(model.speed));
+            let _ = doc_state.store(&model.state_dir);
             None
         }
         Message::PrevWord => {
@@ -92,6 +275,11 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
         }
         Message::NextWord => {
             model.last_word_change = Instant::now();
+            if model.status == Status::Running {
+                let words = model.cursor.current_section().current_chunk().len();
+                model.pace.record(model.word_duration, words);
+                model.ramp_remaining = model.ramp_remaining.saturating_sub(words);
+            }
             if !model.cursor.current_section().next_word() {
                 Some(Message::NextSection)
             } else {
@@ -112,6 +300,48 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
                 None
             }
         }
+        Message::PrevSentence => {
+            if !model.cursor.current_section().prev_sentence() {
+                Some(Message::PrevSection)
+            } else {
+                None
+            }
+        }
+        Message::NextSentence => {
+            if !model.cursor.current_section().next_sentence() {
+                Some(Message::NextSection)
+            } else {
+                None
+            }
+        }
+        Message::PrevParagraph => {
+            if !model.cursor.current_section().prev_paragraph() {
+                Some(Message::PrevSection)
+            } else {
+                None
+            }
+        }
+        Message::NextParagraph => {
+            if !model.cursor.current_section().next_paragraph() {
+                Some(Message::NextSection)
+            } else {
+                None
+            }
+        }
+        Message::Rewind => {
+            // Step back over the words that took the last seconds to read at the
+            // current speed, stopping at the start of the section.
+            let section = model.cursor.current_section();
+            let mut rewound = Duration::ZERO;
+            while rewound < REWIND && section.prev_word() {
+                rewound += model.timing.word_duration(
+                    &section.current_chunk(),
+                    model.speed,
+                    pause(section),
+                );
+            }
+            None
+        }
         Message::PrevSection => {
             model.cursor.prev_section();
             model
@@ -120,7 +350,9 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             None
         }
         Message::NextSection => {
-            model.cursor.next_section();
+            if model.cursor.next_section() {
+                model.ramp_remaining = model.timing.ramp_words;
+            }
             model
                 .table_of_contents_state
                 .select(model.cursor.toc_index());
@@ -128,14 +360,14 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
         }
         Message::DecreaseSpeed => {
             model.speed = min(
-                Duration::from_secs(2),
+                SLOWEST,
                 model.speed.saturating_add(Duration::from_millis(10)),
             );
             None
         }
         Message::IncreaseSpeed => {
             model.speed = max(
-                Duration::from_millis(50),
+                FASTEST,
                 model.speed.saturating_sub(Duration::from_millis(10)),
             );
             None
@@ -147,15 +379,241 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
             }
             Status::Paused => {
                 model.status = Status::Running;
-                Some(Message::NextWord)
+                model.ramp_remaining = model.timing.ramp_words;
+                model.last_word_change = Instant::now();
+                // The paused word isn't read at the pace of the ramp, so leaving it takes
+                // no step of it.
+                if !model.cursor.current_section().next_word() {
+                    Some(Message::NextSection)
+                } else {
+                    None
+                }
             }
         },
+        Message::SetChunkSize(size) => {
+            model.cursor.set_chunk_size(size);
+            None
+        }
+        Message::JumpBack => {
+            let here = position(&mut model.cursor);
+            let (section_index, word_index) = model.jumplist.back(here)?;
+            goto(model, section_index, word_index);
+            None
+        }
+        Message::JumpForward => {
+            let (section_index, word_index) = model.jumplist.forward()?;
+            goto(model, section_index, word_index);
+            None
+        }
+        Message::HelpMessage(msg) => {
+            match msg {
+                HelpMessage::Toggle => {
+                    model.show_help = !model.show_help;
+                    model.help_offset = 0;
+                }
+                HelpMessage::Up => model.help_offset = model.help_offset.saturating_sub(1),
+                // Kept within the rows when the help is drawn.
+                HelpMessage::Down => model.help_offset += 1,
+            }
+            None
+        }
+        Message::ToggleLargeGlyphs => {
+            model.large_glyphs = !model.large_glyphs;
+            None
+        }
+        Message::PromptMessage(msg) => {
+            let prompt = model.prompt.as_mut()?;
+            match msg {
+                PromptMessage::Input(c) => prompt.input.push(c),
+                PromptMessage::DeleteChar => {
+                    prompt.input.pop();
+                }
+                PromptMessage::Submit => {
+                    let prompt = model.prompt.take()?;
+                    return Some(match prompt.kind {
+                        PromptKind::Search => {
+                            Message::SearchMessage(SearchMessage::Find(prompt.input))
+                        }
+                        PromptKind::BookmarkName => {
+                            Message::BookmarkMessage(BookmarkMessage::Add(prompt.input))
+                        }
+                        PromptKind::RenameBookmark(index) => {
+                            Message::BookmarkMessage(BookmarkMessage::Rename(index, prompt.input))
+                        }
+                        PromptKind::Note => {
+                            Message::HighlightMessage(HighlightMessage::Add(prompt.input))
+                        }
+                    });
+                }
+                PromptMessage::Cancel => {
+                    if prompt.kind == PromptKind::Note {
+                        model.mark = None;
+                    }
+                    model.prompt = None;
+                }
+            }
+            None
+        }
+        Message::SearchMessage(msg) => {
+            let search = &mut model.search;
+            match msg {
+                SearchMessage::Open => {
+                    model.status = Status::Paused;
+                    model.prompt = Some(Prompt {
+                        kind: PromptKind::Search,
+                        input: String::new(),
+                    });
+                }
+                SearchMessage::Find(query) => {
+                    search.hits = model.cursor.search(&query);
+                    search.query = query;
+                    let position = (
+                        model.cursor.section_index(),
+                        model.cursor.current_section().word_index(),
+                    );
+                    // Start from the first hit after the current word.
+                    let first = search
+                        .hits
+                        .iter()
+                        .position(|h| (h.section_index, h.word_index) > position)
+                        .unwrap_or(0);
+                    search.hits_state.select(Some(first));
+                    goto_search_hit(model);
+                }
+                SearchMessage::Close => *search = Search::default(),
+                SearchMessage::Next | SearchMessage::Prev if !search.hits.is_empty() => {
+                    let count = search.hits.len();
+                    let selected = search.hits_state.selected().unwrap_or_default();
+                    search
+                        .hits_state
+                        .select(Some(if msg == SearchMessage::Next {
+                            (selected + 1) % count
+                        } else {
+                            (selected + count - 1) % count
+                        }));
+                    goto_search_hit(model);
+                }
+                SearchMessage::Next | SearchMessage::Prev => {}
+            }
+            None
+        }
+        Message::BookmarkMessage(msg) => {
+            let selected = model.bookmarks_state.selected();
+            let count = model.bookmarks.list().len();
+            match msg {
+                BookmarkMessage::New => {
+                    model.status = Status::Paused;
+                    let section_index = model.cursor.section_index();
+                    model.prompt = Some(Prompt {
+                        kind: PromptKind::BookmarkName,
+                        input: model
+                            .cursor
+                            .chapter(section_index)
+                            .unwrap_or_else(|| format!("Section {section_index}")),
+                    });
+                }
+                BookmarkMessage::Add(name) => {
+                    let position = model.bookmarks.add(Bookmark {
+                        name,
+                        section_index: model.cursor.section_index(),
+                        word_index: model.cursor.current_section().word_index(),
+                    });
+                    model.bookmarks_state.select(Some(position));
+                    let _ = model.bookmarks.store(&model.state_dir);
+                }
+                BookmarkMessage::TogglePane => {
+                    model.show_bookmarks = !model.show_bookmarks;
+                    if selected.is_none() && count > 0 {
+                        model.bookmarks_state.select(Some(0));
+                    }
+                }
+                BookmarkMessage::Up => model
+                    .bookmarks_state
+                    .select(selected.map(|s| s.saturating_sub(1))),
+                BookmarkMessage::Down => model
+                    .bookmarks_state
+                    .select(selected.map(|s| (s + 1).min(count.saturating_sub(1)))),
+                BookmarkMessage::Jump => {
+                    if let Some(bookmark) = selected.and_then(|s| model.bookmarks.list().get(s)) {
+                        let (section_index, word_index) =
+                            (bookmark.section_index, bookmark.word_index);
+                        model.status = Status::Paused;
+                        jump(model, section_index, word_index);
+                    }
+                }
+                BookmarkMessage::StartRename => {
+                    if let Some(index) = selected.filter(|s| *s < count) {
+                        model.prompt = Some(Prompt {
+                            kind: PromptKind::RenameBookmark(index),
+                            input: model.bookmarks.list()[index].name.clone(),
+                        });
+                    }
+                }
+                BookmarkMessage::Rename(index, name) => {
+                    model.bookmarks.rename(index, name);
+                    let _ = model.bookmarks.store(&model.state_dir);
+                }
+                BookmarkMessage::Delete => {
+                    if let Some(selected) = selected {
+                        model.bookmarks.remove(selected);
+                        let count = model.bookmarks.list().len();
+                        model
+                            .bookmarks_state
+                            .select((count > 0).then(|| selected.min(count - 1)));
+                        let _ = model.bookmarks.store(&model.state_dir);
+                    }
+                }
+            }
+            None
+        }
+        Message::HighlightMessage(msg) => {
+            let section_index = model.cursor.section_index();
+            let current = model.cursor.current_section().current_words()?;
+            match msg {
+                // The first mark starts the highlight, the second one ends it and asks for
+                // the note.
+                HighlightMessage::Mark => match model.mark {
+                    Some((section, _)) if section == section_index => {
+                        model.status = Status::Paused;
+                        model.prompt = Some(Prompt {
+                            kind: PromptKind::Note,
+                            input: String::new(),
+                        });
+                    }
+                    _ => model.mark = Some((section_index, current.start)),
+                },
+                HighlightMessage::Add(note) => {
+                    let (_, mark) = model.mark.take()?;
+                    let (start_word, end_word) =
+                        (mark.min(current.start), mark.max(current.end - 1));
+                    let quote = model
+                        .cursor
+                        .current_section()
+                        .words()
+                        .skip(start_word)
+                        .take(end_word - start_word + 1)
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    model.notes.add(Highlight {
+                        section_index,
+                        start_word,
+                        end_word,
+                        quote,
+                        note,
+                    });
+                    let _ = model.notes.store(&model.state_dir);
+                }
+            }
+            None
+        }
         Message::TableOfContentsMessage(msg) => {
             model.status = Status::Paused;
             match msg {
                 TableOfContentsMessage::Select => {
-                    if let Some(selected) = model.table_of_contents_state.selected().first() {
-                        model.cursor.goto_section(*selected);
+                    let selected = model.table_of_contents_state.selected();
+                    let here = position(&mut model.cursor);
+                    if model.cursor.goto_toc_entry(&selected) {
+                        model.jumplist.push(here);
                     }
                 }
                 TableOfContentsMessage::Left => model.table_of_contents_state.key_left(),
@@ -172,6 +630,39 @@ fn update(model: &mut Model, msg: Message) -> Option<Message> {
     }
 }
 
+fn goto_search_hit(model: &mut Model) {
+    let search = &model.search;
+    if let Some(hit) = search
+        .hits_state
+        .selected()
+        .and_then(|i| search.hits.get(i))
+    {
+        let (section_index, word_index) = (hit.section_index, hit.word_index);
+        jump(model, section_index, word_index);
+    }
+}
+
+/// Moves to a position, remembering the one left in the jumplist.
+fn jump(model: &mut Model, section_index: usize, word_index: usize) {
+    let here = position(&mut model.cursor);
+    model.jumplist.push(here);
+    goto(model, section_index, word_index);
+}
+
+fn position(cursor: &mut DocumentCursor) -> jumplist::Position {
+    (
+        cursor.section_index(),
+        cursor.current_section().word_index(),
+    )
+}
+
+fn goto(model: &mut Model, section_index: usize, word_index: usize) {
+    model.cursor.goto_word(section_index, word_index);
+    model
+        .table_of_contents_state
+        .select(model.cursor.toc_index());
+}
+
 fn view(model: &mut Model, f: &mut Frame) {
     let word = model
         .cursor
@@ -183,7 +674,11 @@ fn view(model: &mut Model, f: &mut Frame) {
         .margin(1)
         .constraints(
             [
-                Constraint::Max(5),
+                if model.large_glyphs {
+                    Constraint::Percentage(40)
+                } else {
+                    Constraint::Max(5)
+                },
                 Constraint::Percentage(80),
                 Constraint::Max(3),
             ]
@@ -194,23 +689,115 @@ fn view(model: &mut Model, f: &mut Frame) {
         .direction(Direction::Horizontal)
         .constraints(vec![Constraint::Percentage(25), Constraint::Percentage(75)])
         .split(main_layout[1]);
-    f.render_widget(current_word(&word), main_layout[0]);
+    f.render_widget(Block::default().style(model.theme.text), f.size());
+    let current_word = model
+        .large_glyphs
+        .then(|| large_word(&word, main_layout[0], &model.theme))
+        .flatten()
+        .unwrap_or_else(|| current_word(&word, main_layout[0].width, &model.theme));
+    f.render_widget(current_word, main_layout[0]);
+    let panes = 1 + model.show_bookmarks as u32 + !model.search.hits.is_empty() as u32;
+    let side_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Ratio(1, panes); panes as usize])
+        .split(content_layout[0]);
+    let mut side_areas = side_layout.iter().copied();
     f.render_stateful_widget(
-        table_of_contents(model.table_of_contents.clone()),
-        content_layout[0],
+        table_of_contents(model.table_of_contents.clone(), &model.theme),
+        side_areas.next().unwrap(),
         &mut model.table_of_contents_state,
     );
+    if model.show_bookmarks {
+        f.render_stateful_widget(
+            bookmarks(model),
+            side_areas.next().unwrap(),
+            &mut model.bookmarks_state,
+        );
+    }
+    if !model.search.hits.is_empty() {
+        f.render_stateful_widget(
+            search_hits(&model.search, &model.theme),
+            side_areas.next().unwrap(),
+            &mut model.search.hits_state,
+        );
+    }
+    let highlighted = highlighted(model);
     f.render_widget(
-        content(&mut model.cursor, content_layout[1].width),
+        content(
+            &mut model.cursor,
+            content_layout[1].width,
+            &highlighted,
+            &model.theme,
+        ),
         content_layout[1],
     );
-    f.render_widget(status_bar(&model), main_layout[2])
+    f.render_widget(status_bar(model), main_layout[2]);
+    if model.show_help {
+        let rows = model.keymap.help();
+        // As tall as the rows and the borders, when the terminal allows.
+        let area = centered(f.size(), 70, rows.len() as u16 + 2);
+        let shown = area.height.saturating_sub(2) as usize;
+        model.help_offset = model.help_offset.min(rows.len().saturating_sub(shown));
+        f.render_widget(Clear, area);
+        f.render_widget(
+            help(&model.keymap, rows, model.help_offset).style(model.theme.text),
+            area,
+        );
+    }
+}
+
+/// The keys bound to every action, as set in the configuration, from the row `offset`.
+fn help(keymap: &Keymap, rows: Vec<(Vec<String>, &'static str)>, offset: usize) -> Table<'static> {
+    let rows: Vec<Row> = rows
+        .into_iter()
+        .skip(offset)
+        .map(|(keys, description)| {
+            let keys = if keys.is_empty() {
+                "-".to_string()
+            } else {
+                keys.join(", ")
+            };
+            Row::new(vec![keys, description.to_string()])
+        })
+        .collect();
+    let hint = keymap.hint(&[
+        (Action::PrevLine, "scroll up"),
+        (Action::NextLine, "scroll down"),
+    ]);
+    Table::new(rows)
+        .widths(&[Constraint::Percentage(30), Constraint::Percentage(70)])
+        .block(
+            Block::default()
+                .title(format!("Keys ({hint}, any other key: close)"))
+                .borders(Borders::ALL),
+        )
+}
+
+/// An area `width` percent of `area` wide and up to `height` rows high, in its middle.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let height = height.min(area.height);
+    let vertical = Rect {
+        y: area.y + (area.height - height) / 2,
+        height,
+        ..area
+    };
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - width) / 2),
+            Constraint::Percentage(width),
+            Constraint::Percentage((100 - width) / 2),
+        ])
+        .split(vertical)[1]
 }
 
-fn table_of_contents(content: Vec<TreeItem<'static, usize>>) -> Tree<usize> {
+fn table_of_contents(
+    content: Vec<TreeItem<'static, usize>>,
+    theme: &Theme,
+) -> Tree<'static, usize> {
     Tree::new(content)
         .expect("all item identifiers are unique")
-        .highlight_style(Style::default().bg(Color::Yellow))
+        .highlight_style(theme.selection)
         .block(
             Block::default()
                 .title("Table of Contents")
@@ -218,177 +805,506 @@ fn table_of_contents(content: Vec<TreeItem<'static, usize>>) -> Tree<usize> {
         )
 }
 
-fn content(cursor: &mut document::DocumentCursor, width: u16) -> Paragraph {
+fn search_hits(search: &Search, theme: &Theme) -> List<'static> {
+    let items: Vec<ListItem> = search
+        .hits
+        .iter()
+        .map(|hit| {
+            let chapter = hit
+                .chapter
+                .clone()
+                .unwrap_or_else(|| format!("Section {}", hit.section_index));
+            ListItem::new(vec![
+                Line::styled(chapter, theme.heading),
+                Line::raw(hit.snippet.clone()),
+            ])
+        })
+        .collect();
+    let title = format!(
+        "Search: {} ({}/{})",
+        search.query,
+        search.hits_state.selected().map_or(0, |i| i + 1),
+        search.hits.len()
+    );
+    List::new(items)
+        .highlight_style(theme.selection)
+        .block(Block::default().title(title).borders(Borders::ALL))
+}
+
+fn bookmarks(model: &Model) -> List<'static> {
+    let items: Vec<ListItem> = model
+        .bookmarks
+        .list()
+        .iter()
+        .map(|bookmark| {
+            let chapter = model
+                .cursor
+                .chapter(bookmark.section_index)
+                .unwrap_or_else(|| format!("Section {}", bookmark.section_index));
+            ListItem::new(vec![
+                Line::raw(bookmark.name.clone()),
+                Line::styled(
+                    format!("{chapter}, word {}", bookmark.word_index + 1),
+                    model.theme.heading,
+                ),
+            ])
+        })
+        .collect();
+    let hint = model.keymap.hint(&[
+        (Action::BookmarksJump, "jump"),
+        (Action::BookmarksRename, "rename"),
+        (Action::BookmarksDelete, "delete"),
+    ]);
+    List::new(items)
+        .highlight_style(model.theme.selection)
+        .block(
+            Block::default()
+                .title(format!("Bookmarks ({hint})"))
+                .borders(Borders::ALL),
+        )
+}
+
+/// Word ranges of the current section to show highlighted: the saved highlights and
+/// the one being marked.
+fn highlighted(model: &mut Model) -> Vec<RangeInclusive<usize>> {
+    let section_index = model.cursor.section_index();
+    let mut ranges: Vec<RangeInclusive<usize>> = model
+        .notes
+        .list()
+        .iter()
+        .filter(|h| h.section_index == section_index)
+        .map(|h| h.start_word..=h.end_word)
+        .collect();
+    let current = model.cursor.current_section().current_words();
+    if let (Some((section, mark)), Some(current)) = (model.mark, current) {
+        if section == section_index {
+            ranges.push(mark.min(current.start)..=mark.max(current.end - 1));
+        }
+    }
+    ranges
+}
+
+fn content<'a>(
+    cursor: &'a mut document::DocumentCursor,
+    width: u16,
+    highlighted: &[RangeInclusive<usize>],
+    theme: &Theme,
+) -> Paragraph<'a> {
     let mut lines: Vec<Line> = vec![];
     let mut index = 0;
     let current_section = cursor.current_section_or_resize(width as usize - 2);
-    let current_line = current_section.current_line();
+    let current_line = current_section.current_line().map(|l| l.index);
+    let current_words = current_section.current_words().unwrap_or_default();
     let text_lines = current_section.content.lines();
     if let Some(current_line) = current_line {
-        for l in text_lines {
-            let line = if !l.is_empty() && index == current_line.index {
-                let split: Vec<_> = l.split_inclusive(char::is_whitespace).collect();
-                let line: Line = if current_section.word_index() != current_line.first_word_index()
-                {
-                    let pos = current_section.word_index() - current_line.first_word_index();
-                    let pos = split
-                        .iter()
-                        .enumerate()
-                        .filter(|w| !w.1.trim().is_empty())
-                        .nth(pos)
-                        .map(|e| e.0)
-                        .unwrap();
-                    let mut res = vec![Span::raw(split[..pos].join(""))];
-                    res.extend(word_cursor(split[pos]));
-                    res.push(Span::raw(split[pos + 1..].join("")));
-                    res
-                } else {
-                    let mut res = word_cursor(split[0]);
-                    res.push(Span::raw(split[1..].join("")));
-                    res
-                }
-                .into();
-
-                line
+        let style = |word_index| {
+            if current_words.contains(&word_index) {
+                Some(theme.cursor)
+            } else if highlighted.iter().any(|h| h.contains(&word_index)) {
+                Some(theme.highlight)
             } else {
-                Line::raw(l)
+                None
+            }
+        };
+        for l in text_lines {
+            let line = match current_section.line(index) {
+                Some(line) if !l.is_empty() => styled_line(line, style),
+                _ => Line::raw(l),
             };
             lines.push(line);
             if !l.is_empty() {
                 index += 1;
             }
         }
-        if current_line.index > 3 {
-            lines = lines.into_iter().skip(current_line.index - 3).collect();
+        if current_line > 3 {
+            lines = lines.into_iter().skip(current_line - 3).collect();
         }
     }
 
-    Paragraph::new(lines)
-        .block(Block::default().title("Content").borders(Borders::ALL))
-        .style(Style::default().fg(Color::White).bg(Color::Black))
+    Paragraph::new(lines).block(Block::default().title("Content").borders(Borders::ALL))
 }
 
-fn word_cursor(word: &str) -> Vec<Span> {
-    let mut chars = word.chars();
-    let word: String = chars
-        .by_ref()
-        .peeking_take_while(|c| !c.is_whitespace())
-        .collect();
-    let postfix: String = chars.by_ref().collect();
-    vec![
-        Span::styled(word, Style::default().bg(Color::LightYellow)),
-        Span::raw(postfix),
-    ]
+/// A line of the section with its words styled, consecutive words with the same style
+/// sharing a span.
+fn styled_line(line: &document::Line, style: impl Fn(usize) -> Option<Style>) -> Line<'_> {
+    let content = line.content.as_str();
+    let mut spans = vec![];
+    let mut shown = 0;
+    let mut run: Option<(std::ops::Range<usize>, Style)> = None;
+    for (range, word_index) in line.words.iter().zip(&line.word_indexes) {
+        let style = style(*word_index);
+        match (&mut run, style) {
+            (Some((run, run_style)), Some(style)) if *run_style == style => run.end = range.end,
+            _ => {
+                if let Some((run, run_style)) = run.take() {
+                    spans.push(Span::raw(&content[shown..run.start]));
+                    spans.push(Span::styled(&content[run.clone()], run_style));
+                    shown = run.end;
+                }
+                run = style.map(|s| (range.clone(), s));
+            }
+        }
+    }
+    if let Some((run, run_style)) = run {
+        spans.push(Span::raw(&content[shown..run.start]));
+        spans.push(Span::styled(&content[run.clone()], run_style));
+        shown = run.end;
+    }
+    spans.push(Span::raw(&content[shown..]));
+    spans.into()
 }
 
-fn current_word(word: impl ToString) -> Paragraph<'static> {
-    let word = word.to_string();
+/// Shows the word with its pivot letter always in the same column, so that the eyes
+/// don't have to move from word to word.
+fn current_word(word: &str, width: u16, theme: &Theme) -> Paragraph<'static> {
+    let column = width.saturating_sub(2) as usize * 2 / 5;
     let word_text: Line = if word.is_empty() {
         Line::raw("")
     } else {
-        let (first_half, center, second_half) = split_word(word.to_string().as_str());
+        let (mut first_half, center, second_half) = split_word(word);
+        while first_half.width() > column {
+            first_half.remove(0);
+        }
         vec![
-            Span::raw(first_half),
-            Span::styled(center, Style::default().fg(Color::Red)),
+            Span::raw(" ".repeat(column - first_half.width()) + &first_half),
+            Span::styled(center, theme.pivot),
             Span::raw(second_half),
         ]
         .into()
     };
-    let gauge: Text = vec!["\\/".into(), word_text, "/\\".into()].into();
-    Paragraph::new(gauge)
-        .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .title(format!("Current Word"))
-                .borders(Borders::ALL),
-        )
-        .style(Style::default().fg(Color::White).bg(Color::Black))
+    let marker = |m: &str| Line::raw(" ".repeat(column) + m);
+    let gauge: Text = vec![marker("│"), word_text, marker("│")].into();
+    Paragraph::new(gauge).block(Block::default().title("Current Word").borders(Borders::ALL))
 }
 
-fn status_bar(model: &Model) -> Paragraph {
-    let status = format!(
-        "Status: {} Speed: {} wpm Position {}/{}",
-        model.status.to_string(),
-        60000 / model.speed.as_millis(),
+/// Shows the word in glyphs as large as the area allows, with the middle of the pivot
+/// letter in the column `current_word` puts it in. Nothing is shown for words with
+/// characters the glyphs lack.
+fn large_word(word: &str, area: Rect, theme: &Theme) -> Option<Paragraph<'static>> {
+    if word.is_empty() {
+        return None;
+    }
+    let width = area.width.saturating_sub(2) as usize;
+    let column = width * 2 / 5;
+    // Below the borders and the markers.
+    let height = area.height.saturating_sub(4) as usize;
+    let (first_half, center, second_half) = split_word(word);
+    let fits = |scale: usize| {
+        let first = glyphs::width(&first_half, scale)?;
+        let pivot = glyphs::width(&center, scale)?;
+        let second = glyphs::width(&second_half, scale)?;
+        // The spacing after a glyph is part of its width.
+        let offset = first + (pivot - scale) / 2;
+        let padding = column.checked_sub(offset)?;
+        (padding + first + pivot + second - scale <= width).then_some(padding)
+    };
+    let (scale, padding) = (1..=height * 2 / glyphs::HEIGHT)
+        .rev()
+        .find_map(|scale| fits(scale).map(|padding| (scale, padding)))?;
+
+    let [first, pivot, second] =
+        [&first_half, &center, &second_half].map(|part| glyphs::lines(part, scale));
+    let marker = |m: &str| Line::raw(" ".repeat(column) + m);
+    let mut lines = vec![marker("│")];
+    lines.resize(1 + (height - pivot.len()) / 2, Line::raw(""));
+    for ((first, pivot), second) in first.into_iter().zip(pivot).zip(second) {
+        lines.push(
+            vec![
+                Span::raw(" ".repeat(padding) + &first),
+                Span::styled(pivot, theme.pivot),
+                Span::raw(second),
+            ]
+            .into(),
+        );
+    }
+    lines.resize(1 + height, Line::raw(""));
+    lines.push(marker("│"));
+    Some(Paragraph::new(lines).block(Block::default().title("Current Word").borders(Borders::ALL)))
+}
+
+fn status_bar(model: &Model) -> Paragraph<'_> {
+    if let Some(prompt) = &model.prompt {
+        let title = match prompt.kind {
+            PromptKind::Search => "Search",
+            PromptKind::BookmarkName => "Bookmark name",
+            PromptKind::RenameBookmark(_) => "Rename bookmark",
+            PromptKind::Note => "Note",
+        };
+        return Paragraph::new(prompt.input.as_str())
+            .block(Block::default().title(title).borders(Borders::ALL));
+    }
+    let mut status = format!(
+        "Status: {} Speed: {:.0} wpm Average: {} wpm Position {}/{}",
+        model.status,
+        wpm(model.speed),
+        model
+            .pace
+            .wpm()
+            .map_or("-".to_string(), |wpm| wpm.to_string()),
         model.cursor.section_index(),
         model.cursor.sections(),
     );
+    if let Some(rate) = model
+        .timing
+        .ramp_rate(model.ramp_remaining)
+        .filter(|_| model.status == Status::Running)
+    {
+        status += &format!(" Ramp: {:.0}%", rate * 100.0);
+    }
+    if model.mark.is_some() {
+        let hint = model
+            .keymap
+            .hint(&[(Action::Highlight, "end the highlight")]);
+        status += &format!(" Marking ({hint})");
+    }
     Paragraph::new(status).block(Block::default().title("Status").borders(Borders::ALL))
 }
 
+fn word_duration(model: &mut Model) -> Duration {
+    let section = model.cursor.current_section();
+    let base = model.timing.ramp(model.speed, model.ramp_remaining);
+    model
+        .timing
+        .word_duration(&section.current_chunk(), base, pause(section))
+}
+
+fn pause(section: &SectionCursor) -> Pause {
+    if section.at_section_end() {
+        Pause::Section
+    } else if section.at_paragraph_end() {
+        Pause::Paragraph
+    } else {
+        Pause::None
+    }
+}
+
+/// Speed at which the rest of the current section takes `minutes` to read, accounting
+/// for the longer words and the pauses.
+fn target_speed(section: &SectionCursor, timing: &Timing, minutes: f64) -> Duration {
+    let mut section = section.clone();
+    let (mut factors, mut pauses) = (0.0, Duration::ZERO);
+    loop {
+        let chunk = section.current_chunk();
+        if chunk.is_empty() {
+            break;
+        }
+        factors += timing
+            .word_duration(&chunk, Duration::from_secs(1), Pause::None)
+            .as_secs_f64();
+        pauses += timing.pause(pause(&section));
+        if !section.next_word() {
+            break;
+        }
+    }
+    if factors == 0.0 {
+        return SLOWEST;
+    }
+    let available = Duration::from_secs_f64(minutes * 60.0).saturating_sub(pauses);
+    available.div_f64(factors).clamp(FASTEST, SLOWEST)
+}
+
 fn handle_event(model: &Model) -> anyhow::Result<Option<Message>> {
-    let timeout = model.speed.saturating_sub(model.last_word_change.elapsed());
+    let timeout = model
+        .word_duration
+        .saturating_sub(model.last_word_change.elapsed());
     if crossterm::event::poll(timeout)? {
         if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
-            match key.code {
-                crossterm::event::KeyCode::Char('q') => Ok(Some(Message::Quit)),
-                crossterm::event::KeyCode::Right => Ok(Some(Message::NextWord)),
-                crossterm::event::KeyCode::Left => Ok(Some(Message::PrevWord)),
-                crossterm::event::KeyCode::Up => Ok(Some(Message::PrevLine)),
-                crossterm::event::KeyCode::Down => Ok(Some(Message::NextLine)),
-                crossterm::event::KeyCode::PageUp => Ok(Some(Message::PrevSection)),
-                crossterm::event::KeyCode::PageDown => Ok(Some(Message::NextSection)),
-                crossterm::event::KeyCode::Char('+') => Ok(Some(Message::IncreaseSpeed)),
-                crossterm::event::KeyCode::Char('-') => Ok(Some(Message::DecreaseSpeed)),
-                crossterm::event::KeyCode::Char(' ') => Ok(Some(Message::ToggleStatus)),
-                crossterm::event::KeyCode::Char('a') => Ok(Some(Message::TableOfContentsMessage(
-                    TableOfContentsMessage::Left,
-                ))),
-                crossterm::event::KeyCode::Char('d') => Ok(Some(Message::TableOfContentsMessage(
-                    TableOfContentsMessage::Right,
-                ))),
-                crossterm::event::KeyCode::Char('s') => Ok(Some(Message::TableOfContentsMessage(
-                    TableOfContentsMessage::Down,
-                ))),
-                crossterm::event::KeyCode::Char('w') => Ok(Some(Message::TableOfContentsMessage(
-                    TableOfContentsMessage::Up,
-                ))),
-                crossterm::event::KeyCode::Enter => Ok(Some(Message::TableOfContentsMessage(
-                    TableOfContentsMessage::Select,
-                ))),
-                _ => Ok(None),
+            if model.show_help {
+                let msg = match model.keymap.action(Mode::Reading, key) {
+                    Some(Action::PrevLine) => HelpMessage::Up,
+                    Some(Action::NextLine) => HelpMessage::Down,
+                    _ => HelpMessage::Toggle,
+                };
+                return Ok(Some(Message::HelpMessage(msg)));
+            }
+            if model.prompt.is_some() {
+                return Ok(match model.keymap.action(Mode::Prompt, key) {
+                    Some(action) => message(action),
+                    None => match key.code {
+                        crossterm::event::KeyCode::Char(c) => {
+                            Some(Message::PromptMessage(PromptMessage::Input(c)))
+                        }
+                        _ => None,
+                    },
+                });
             }
+            // The bookmarks pane takes its keys while it is open.
+            if model.show_bookmarks {
+                if let Some(action) = model.keymap.action(Mode::Bookmarks, key) {
+                    return Ok(message(action));
+                }
+            }
+            Ok(model.keymap.action(Mode::Reading, key).and_then(message))
         } else {
             Ok(None)
         }
     } else {
-        if model.status == Status::Running && model.last_word_change.elapsed() >= model.speed {
+        if model.status == Status::Running
+            && model.last_word_change.elapsed() >= model.word_duration
+        {
             return Ok(Some(Message::NextWord));
         }
         Ok(None)
     }
 }
 
+/// The message sent for an action bound to a key, the library ones having none while
+/// reading.
+fn message(action: Action) -> Option<Message> {
+    Some(match action {
+        Action::Quit => Message::Quit,
+        Action::ToggleStatus => Message::ToggleStatus,
+        Action::PrevWord => Message::PrevWord,
+        Action::NextWord => Message::NextWord,
+        Action::PrevLine => Message::PrevLine,
+        Action::NextLine => Message::NextLine,
+        Action::PrevSentence => Message::PrevSentence,
+        Action::NextSentence => Message::NextSentence,
+        Action::PrevParagraph => Message::PrevParagraph,
+        Action::NextParagraph => Message::NextParagraph,
+        Action::Rewind => Message::Rewind,
+        Action::PrevSection => Message::PrevSection,
+        Action::NextSection => Message::NextSection,
+        Action::IncreaseSpeed => Message::IncreaseSpeed,
+        Action::DecreaseSpeed => Message::DecreaseSpeed,
+        Action::Chunk1 => Message::SetChunkSize(1),
+        Action::Chunk2 => Message::SetChunkSize(2),
+        Action::Chunk3 => Message::SetChunkSize(3),
+        Action::Chunk4 => Message::SetChunkSize(4),
+        Action::Chunk5 => Message::SetChunkSize(5),
+        Action::TocUp => Message::TableOfContentsMessage(TableOfContentsMessage::Up),
+        Action::TocDown => Message::TableOfContentsMessage(TableOfContentsMessage::Down),
+        Action::TocLeft => Message::TableOfContentsMessage(TableOfContentsMessage::Left),
+        Action::TocRight => Message::TableOfContentsMessage(TableOfContentsMessage::Right),
+        Action::TocSelect => Message::TableOfContentsMessage(TableOfContentsMessage::Select),
+        Action::Search => Message::SearchMessage(SearchMessage::Open),
+        Action::NextHit => Message::SearchMessage(SearchMessage::Next),
+        Action::PrevHit => Message::SearchMessage(SearchMessage::Prev),
+        Action::CloseSearch => Message::SearchMessage(SearchMessage::Close),
+        Action::Bookmark => Message::BookmarkMessage(BookmarkMessage::New),
+        Action::Bookmarks => Message::BookmarkMessage(BookmarkMessage::TogglePane),
+        Action::Highlight => Message::HighlightMessage(HighlightMessage::Mark),
+        Action::JumpBack => Message::JumpBack,
+        Action::JumpForward => Message::JumpForward,
+        Action::LargeGlyphs => Message::ToggleLargeGlyphs,
+        Action::Help => Message::HelpMessage(HelpMessage::Toggle),
+        Action::BookmarksUp => Message::BookmarkMessage(BookmarkMessage::Up),
+        Action::BookmarksDown => Message::BookmarkMessage(BookmarkMessage::Down),
+        Action::BookmarksJump => Message::BookmarkMessage(BookmarkMessage::Jump),
+        Action::BookmarksRename => Message::BookmarkMessage(BookmarkMessage::StartRename),
+        Action::BookmarksDelete => Message::BookmarkMessage(BookmarkMessage::Delete),
+        Action::BookmarksClose => Message::BookmarkMessage(BookmarkMessage::TogglePane),
+        Action::PromptSubmit => Message::PromptMessage(PromptMessage::Submit),
+        Action::PromptCancel => Message::PromptMessage(PromptMessage::Cancel),
+        Action::PromptDeleteChar => Message::PromptMessage(PromptMessage::DeleteChar),
+        Action::LibraryUp
+        | Action::LibraryDown
+        | Action::LibraryOpen
+        | Action::LibrarySort
+        | Action::LibraryFilter
+        | Action::LibraryQuit => return None,
+    })
+}
+
 fn main() -> anyhow::Result<()> {
     initialize_panic_handler();
 
     let args = Args::parse();
+    if let Some(Command::ExportNotes { path, format }) = &args.command {
+        return export_notes(path, *format);
+    }
+    let config = config::Config::load()?;
+    let keymap = config.keymap()?;
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let theme = config.theme(no_color)?;
+    // The theme already follows NO_COLOR, unless one is configured, which overrides it.
+    crossterm::style::force_color_output(true);
+
+    let state_dir = state::state_dir()?;
+    let path = match args.path.clone() {
+        Some(path) if path != Path::new("-") => Some(path),
+        None if std::io::stdin().is_terminal() => {
+            match library_screen::run(&state_dir, &theme, &keymap)? {
+                Some(path) => Some(path),
+                None => return Ok(()),
+            }
+        }
+        _ => None,
+    };
+    // Once stdin has been consumed, crossterm reads the keyboard from /dev/tty.
+    let doc = match path.as_deref() {
+        Some(path) => {
+            document::open(path).with_context(|| format!("unable to open {}", path.display()))?
+        }
+        None => document::read(std::io::stdin().lock()).context("unable to read stdin")?,
+    };
+    // Documents read from a pipe can't be opened again from the library.
+    let library_path = path.as_deref().map(std::fs::canonicalize).transpose()?;
 
     crossterm::terminal::enable_raw_mode()?;
     crossterm::execute!(std::io::stderr(), crossterm::terminal::EnterAlternateScreen)?;
 
     let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
 
-    let doc = document::EpubDoc::open(&args.path).expect("unable to open the epub");
-    let table_of_contents = doc.table_of_contents();
-    let table_of_contents: Vec<TreeItem<'static, usize>> =
-        table_of_contents.iter().map(Into::into).collect();
+    let table_of_contents = table_of_contents_items(doc.table_of_contents());
 
-    std::fs::create_dir_all(CONFIG_PATH)?;
-    let doc_state = DocState::load(
-        Path::new(CONFIG_PATH),
-        doc.unique_identifier.clone().unwrap(),
-    );
-    let cursor = DocumentCursor::new(doc, doc_state);
+    let identifier = doc.identifier();
+    let doc_state = DocState::load(&state_dir, identifier.clone());
+    let saved_wpm = doc_state.wpm;
+    let bookmarks = Bookmarks::load(&state_dir, identifier.clone());
+    let notes = Notes::load(&state_dir, identifier.clone());
+    let mut cursor = DocumentCursor::new(doc, doc_state);
+    let library_entry = library_path.map(|path| LibraryEntry {
+        path,
+        title: cursor.metadata().title,
+        author: cursor.metadata().author,
+        last_opened: SystemTime::now(),
+        progress: cursor.progress(),
+        words_left: Some(cursor.words_left()),
+    });
+    if let Some(entry) = &library_entry {
+        let _ = update_library(&state_dir, identifier.clone(), entry.clone());
+    }
+    cursor.set_chunk_size(args.chunk as usize);
+    let timing = Timing {
+        paragraph_pause: args.paragraph_pause,
+        section_pause: args.section_pause,
+        ramp_words: args.ramp_words,
+        ramp_start: args.ramp_start as f64 / 100.0,
+    };
+    let speed = match (args.speed, args.wpm, args.target_minutes) {
+        (Some(speed), _, _) => speed.clamp(FASTEST, SLOWEST),
+        (_, Some(wpm), _) => speed(wpm),
+        (_, _, Some(minutes)) => target_speed(cursor.current_section(), &timing, minutes),
+        _ => speed(saved_wpm.unwrap_or(DEFAULT_WPM)),
+    };
     let mut table_of_contents_state = TreeState::default();
     table_of_contents_state.select(cursor.toc_index());
     let mut model = Model {
         should_quit: false,
+        state_dir,
         cursor,
         table_of_contents,
         table_of_contents_state,
         last_word_change: Instant::now(),
-        speed: args.speed,
+        speed,
+        timing,
+        word_duration: speed,
+        pace: Pace::default(),
+        ramp_remaining: 0,
+        prompt: None,
+        search: Search::default(),
+        bookmarks,
+        bookmarks_state: ListState::default(),
+        show_bookmarks: false,
+        notes,
+        mark: None,
+        jumplist: Jumplist::default(),
+        keymap,
+        show_help: false,
+        help_offset: 0,
+        large_glyphs: args.large_glyphs,
+        theme,
         status: Status::Paused,
     };
     loop {
@@ -399,38 +1315,92 @@ fn main() -> anyhow::Result<()> {
         if model.should_quit {
             break;
         }
+        model.word_duration = word_duration(&mut model);
         let mut current_msg = handle_event(&model)?;
-        while current_msg != None {
+        while current_msg.is_some() {
             current_msg = update(&mut model, current_msg.unwrap());
         }
     }
 
+    if let Some(entry) = library_entry {
+        let entry = LibraryEntry {
+            progress: model.cursor.progress(),
+            words_left: Some(model.cursor.words_left()),
+            ..entry
+        };
+        let _ = update_library(&model.state_dir, identifier, entry);
+    }
+
     crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
     crossterm::terminal::disable_raw_mode()?;
     Ok(())
 }
 
+/// Records a document in the library index, loaded again to keep the changes made by
+/// other instances.
+fn update_library(state_dir: &Path, identifier: String, entry: LibraryEntry) -> anyhow::Result<()> {
+    let mut library = Library::load(state_dir);
+    library.update(identifier, entry);
+    library.store(state_dir)
+}
+
+fn export_notes(path: &Path, format: ExportFormat) -> anyhow::Result<()> {
+    let doc = document::open(path).with_context(|| format!("unable to open {}", path.display()))?;
+    let notes = Notes::load(&state::state_dir()?, doc.identifier());
+    let title = doc.metadata().title;
+    let cursor = DocumentCursor::new(doc, DocState::new(String::new()));
+    let exported = notes::export(&notes, title.as_deref(), |s| cursor.chapter(s), format)?;
+    println!("{exported}");
+    Ok(())
+}
+
+/// Splits a word around its optimal recognition point, a letter slightly left of its
+/// middle that moves further right as words get longer. Punctuation is not counted.
 fn split_word(word: &str) -> (String, String, String) {
-    let mid = (word.len() - 1) / 2;
-    let center = word.chars().nth(mid).unwrap_or_default().to_string();
-    let first_half = word.chars().take(mid).collect();
-    let second_half = word.chars().skip(mid + 1).collect();
-    (first_half, center, second_half)
+    let chars: Vec<char> = word.chars().collect();
+    let leading = chars.iter().take_while(|c| !c.is_alphanumeric()).count();
+    let trailing = chars[leading..]
+        .iter()
+        .rev()
+        .take_while(|c| !c.is_alphanumeric())
+        .count();
+    let pivot = leading
+        + match chars.len() - leading - trailing {
+            0..=1 => 0,
+            2..=5 => 1,
+            6..=9 => 2,
+            10..=13 => 3,
+            _ => 4,
+        };
+    // Chunks of several words can have a space at that position.
+    let pivot = (pivot..chars.len())
+        .find(|i| chars[*i].is_alphanumeric())
+        .unwrap_or(chars.len() - 1);
+    (
+        chars[..pivot].iter().collect(),
+        chars[pivot].to_string(),
+        chars[pivot + 1..].iter().collect(),
+    )
 }
 
-impl<'a> From<&TableOfContentNode> for TreeItem<'a, usize> {
-    fn from(value: &TableOfContentNode) -> Self {
-        if value.children.is_empty() {
-            TreeItem::new_leaf(value.index, value.name.clone())
-        } else {
-            TreeItem::new(
-                value.index,
-                value.name.clone(),
-                value.children.iter().map(Into::into).collect(),
-            )
-            .unwrap()
-        }
-    }
+/// Tree items are identified by their position among their siblings, since entries
+/// of the same section can share their section index.
+fn table_of_contents_items(toc: &[TableOfContentNode]) -> Vec<TreeItem<'static, usize>> {
+    toc.iter()
+        .enumerate()
+        .map(|(i, node)| {
+            if node.children.is_empty() {
+                TreeItem::new_leaf(i, node.name.clone())
+            } else {
+                TreeItem::new(
+                    i,
+                    node.name.clone(),
+                    table_of_contents_items(&node.children),
+                )
+                .unwrap()
+            }
+        })
+        .collect()
 }
 
 pub fn initialize_panic_handler() {
@@ -441,3 +1411,101 @@ pub fn initialize_panic_handler() {
         original_hook(panic_info);
     }));
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("a", ("", "a", ""))]
+    #[case("word", ("w", "o", "rd"))]
+    #[case("\"reading,\"", ("\"re", "a", "ding,\""))]
+    #[case("recognition", ("rec", "o", "gnition"))]
+    #[case("one two three", ("one ", "t", "wo three"))]
+    fn it_splits_words_on_the_recognition_point(
+        #[case] word: &str,
+        #[case] expected: (&str, &str, &str),
+    ) {
+        let (first_half, center, second_half) = split_word(word);
+
+        check!((first_half.as_str(), center.as_str(), second_half.as_str()) == expected);
+    }
+
+    #[rstest]
+    fn it_keeps_the_pivot_in_place_in_large_glyphs() {
+        use ratatui::{buffer::Buffer, widgets::Widget};
+        let area = Rect::new(0, 0, 100, 16);
+        let theme = Theme::preset(theme::Preset::Dark);
+        let_assert!(Some(word) = large_word("reading", area, &theme));
+        let mut buffer = Buffer::empty(area);
+        word.render(area, &mut buffer);
+
+        // The pivot is in the column of the markers, past the left border.
+        let column = 1 + 98 * 2 / 5;
+        check!(buffer.get(column, 1).symbol == "│");
+        let pivot: Vec<u16> = (0..area.width)
+            .filter(|x| {
+                (2..14).any(|y| {
+                    let cell = buffer.get(*x, y);
+                    cell.fg == theme.pivot.fg.unwrap() && cell.symbol != " "
+                })
+            })
+            .collect();
+        check!(pivot.len() > 4);
+        check!(pivot[0] + pivot[pivot.len() - 1] + 1 == 2 * column);
+        check!(large_word("日本", area, &theme).is_none());
+    }
+
+    #[rstest]
+    fn it_fits_the_help_in_the_terminal() {
+        let area = Rect::new(0, 0, 100, 30);
+
+        check!(centered(area, 70, 12) == Rect::new(15, 9, 70, 12));
+        check!(centered(area, 70, 60) == Rect::new(15, 0, 70, 30));
+    }
+
+    #[rstest]
+    fn it_accepts_one_speed_setting() {
+        let_assert!(
+            Ok(args) = Args::try_parse_from(["word-runner", "--wpm", "450.5", "book.epub"])
+        );
+        check!(args.wpm == Some(450.5));
+        check!(
+            Args::try_parse_from(["word-runner", "-s", "200", "-w", "300", "book.epub"]).is_err()
+        );
+        check!(Args::try_parse_from(["word-runner", "-w", "0", "book.epub"]).is_err());
+        check!(Args::try_parse_from(["word-runner", "-c", "0", "book.epub"]).is_err());
+        let_assert!(
+            Err(error) =
+                Args::try_parse_from(["word-runner", "--target-minutes", "0", "book.epub"])
+        );
+        check!(error.to_string().contains("minutes"));
+        check!(Args::try_parse_from(["word-runner", "-c", "6", "book.epub"]).is_err());
+    }
+
+    #[rstest]
+    #[case(300.0, Duration::from_millis(200))]
+    #[case(1e-20, SLOWEST)]
+    #[case(1e20, FASTEST)]
+    #[case(f64::NAN, SLOWEST)]
+    fn it_keeps_the_speed_in_range(#[case] words_per_minute: f64, #[case] expected: Duration) {
+        check!(speed(words_per_minute) == expected);
+    }
+
+    #[rstest]
+    fn it_picks_the_speed_to_finish_a_section() {
+        let doc = document::MarkdownDoc::parse("One two three four.\n");
+        let mut cursor = DocumentCursor::new(Box::new(doc), DocState::new("xxxx".to_string()));
+        let timing = Timing {
+            paragraph_pause: Duration::ZERO,
+            section_pause: Duration::from_millis(500),
+            ramp_words: 0,
+            ramp_start: 1.0,
+        };
+
+        let speed = target_speed(cursor.current_section(), &timing, 1.5 / 60.0);
+        check!(speed.as_millis() == 200);
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::Path;
+
+use crate::state::{document_path, read_document, store_json};
+
+/// A highlighted passage of a section, with the note attached to it.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Highlight {
+    pub section_index: usize,
+    /// Index of the first highlighted word.
+    pub start_word: usize,
+    /// Index of the last highlighted word.
+    pub end_word: usize,
+    pub quote: String,
+    pub note: String,
+}
+
+/// The highlights of a document, kept in reading order and stored next to its state.
+#[derive(Debug, Default)]
+pub struct Notes {
+    identifier: String,
+    highlights: Vec<Highlight>,
+}
+
+impl Notes {
+    pub fn load(state_dir: &Path, identifier: String) -> Self {
+        let highlights = read_document(state_dir, &identifier, Some("notes"))
+            .and_then(|h| serde_json::from_str(&h).ok())
+            .unwrap_or_default();
+        Self {
+            identifier,
+            highlights,
+        }
+    }
+
+    pub fn store(&self, state_dir: &Path) -> anyhow::Result<()> {
+        let path = document_path(state_dir, &self.identifier, Some("notes"));
+        store_json(&path, &self.highlights)
+    }
+
+    pub fn list(&self) -> &[Highlight] {
+        &self.highlights
+    }
+
+    pub fn add(&mut self, highlight: Highlight) {
+        let position = self.highlights.partition_point(|h| {
+            (h.section_index, h.start_word) <= (highlight.section_index, highlight.start_word)
+        });
+        self.highlights.insert(position, highlight);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Md,
+    Json,
+}
+
+/// A highlight as it is shared, with the chapter it was taken from.
+#[derive(serde::Serialize)]
+struct ExportedNote<'a> {
+    chapter: Option<String>,
+    quote: &'a str,
+    note: &'a str,
+}
+
+/// Writes the highlights as a Markdown document with a heading per chapter, or as a
+/// JSON list. `chapter` gives the name of the chapter a section belongs to.
+pub fn export(
+    notes: &Notes,
+    title: Option<&str>,
+    chapter: impl Fn(usize) -> Option<String>,
+    format: ExportFormat,
+) -> anyhow::Result<String> {
+    let exported: Vec<ExportedNote> = notes
+        .list()
+        .iter()
+        .map(|h| ExportedNote {
+            chapter: chapter(h.section_index),
+            quote: &h.quote,
+            note: &h.note,
+        })
+        .collect();
+    if format == ExportFormat::Json {
+        return Ok(serde_json::to_string_pretty(&exported)?);
+    }
+
+    let mut md = format!("# {}\n", title.unwrap_or("Notes"));
+    let mut current_chapter = None;
+    for note in &exported {
+        if note.chapter.is_some() && note.chapter != current_chapter {
+            md.push_str(&format!(
+                "\n## {}\n",
+                note.chapter.as_deref().unwrap_or_default()
+            ));
+            current_chapter = note.chapter.clone();
+        }
+        md.push_str(&format!("\n> {}\n", note.quote));
+        if !note.note.is_empty() {
+            md.push_str(&format!("\n{}\n", note.note));
+        }
+    }
+    Ok(md)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_stores_highlights_in_reading_order() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut notes = Notes::load(dir, "book".to_string());
+        check!(notes.list().is_empty());
+
+        notes.add(highlight(2, 10, "Later.", ""));
+        notes.add(highlight(1, 4, "Earlier.", "note"));
+        notes.store(dir).unwrap();
+
+        let notes = Notes::load(dir, "book".to_string());
+        check!(
+            notes.list()
+                == [
+                    highlight(1, 4, "Earlier.", "note"),
+                    highlight(2, 10, "Later.", "")
+                ]
+        );
+    }
+
+    #[rstest]
+    fn it_exports_notes_by_chapter() {
+        let mut notes = Notes::default();
+        notes.add(highlight(1, 0, "Think small.", "Start with tiny problems."));
+        notes.add(highlight(1, 20, "Say I don't know.", ""));
+        notes.add(highlight(3, 5, "Quit.", ""));
+        let chapter = |section| Some(format!("Chapter {section}"));
+
+        let md = export(&notes, Some("Freak"), chapter, ExportFormat::Md).unwrap();
+        check!(
+            md == "# Freak\n\n## Chapter 1\n\n> Think small.\n\nStart with tiny problems.\n\n\
+                   > Say I don't know.\n\n## Chapter 3\n\n> Quit.\n"
+        );
+
+        let json = export(&notes, None, chapter, ExportFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        check!(json[2]["chapter"] == "Chapter 3");
+        check!(json[0]["note"] == "Start with tiny problems.");
+    }
+
+    fn highlight(section_index: usize, start_word: usize, quote: &str, note: &str) -> Highlight {
+        Highlight {
+            section_index,
+            start_word,
+            end_word: start_word + 2,
+            quote: quote.to_string(),
+            note: note.to_string(),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// Directory earlier versions kept the reading state in, relative to the working directory.
+const LEGACY_STATE_DIR: &str = ".config";
+
+/// Directory the reading state of every document is kept in, `$XDG_STATE_HOME/word-runner`
+/// or the local data directory on systems without one.
+pub fn state_dir() -> anyhow::Result<PathBuf> {
+    let dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .context("unable to find a directory to keep the reading state in")?
+        .join("word-runner");
+    std::fs::create_dir_all(&dir).with_context(|| format!("unable to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Path of a file of a document's state, `extension` telling its kind apart. Identifiers
+/// can be URNs, URLs or paths, so every byte that isn't safe in a file name is
+/// percent-encoded, leaving plain identifiers such as UUIDs and hashes as they are.
+pub fn document_path(state_dir: &Path, identifier: &str, extension: Option<&str>) -> PathBuf {
+    let mut name = String::new();
+    for (i, byte) in identifier.bytes().enumerate() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_') || (byte == b'.' && i > 0) {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    if let Some(extension) = extension {
+        name = format!("{name}.{extension}");
+    }
+    state_dir.join(name)
+}
+
+/// Reads a file of a document's state, falling back to the `.config` directory earlier
+/// versions kept it in.
+pub fn read_document(
+    state_dir: &Path,
+    identifier: &str,
+    extension: Option<&str>,
+) -> Option<String> {
+    read_document_from(
+        state_dir,
+        Path::new(LEGACY_STATE_DIR),
+        identifier,
+        extension,
+    )
+}
+
+fn read_document_from(
+    state_dir: &Path,
+    legacy_dir: &Path,
+    identifier: &str,
+    extension: Option<&str>,
+) -> Option<String> {
+    std::fs::read_to_string(document_path(state_dir, identifier, extension))
+        .ok()
+        .or_else(|| {
+            // Earlier versions used the identifier as it is, which is only read back when it
+            // is a plain file name.
+            let name = match extension {
+                Some(extension) => format!("{identifier}.{extension}"),
+                None => identifier.to_string(),
+            };
+            let mut components = Path::new(&name).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(_)), None) => {
+                    std::fs::read_to_string(legacy_dir.join(name)).ok()
+                }
+                _ => None,
+            }
+        })
+}
+
+/// Writes a value as JSON to a temporary file that then replaces `path`, so that a crash
+/// while writing leaves the previous content in place.
+pub fn store_json(path: &Path, value: &impl serde::Serialize) -> anyhow::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    let temporary = PathBuf::from(temporary);
+    let file = File::create(&temporary)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, value)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_replaces_stored_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("book");
+
+        store_json(&path, &vec![1, 2, 3]).unwrap();
+        store_json(&path, &vec![4]).unwrap();
+
+        check!(std::fs::read_to_string(&path).unwrap() == "[4]");
+        check!(std::fs::read_dir(dir).unwrap().count() == 1);
+    }
+
+    #[rstest]
+    #[case(
+        "c7af4885-7934-4428-81c5-f75ff6c954f2",
+        None,
+        "c7af4885-7934-4428-81c5-f75ff6c954f2"
+    )]
+    #[case("urn:isbn:978-0", Some("notes"), "urn%3Aisbn%3A978-0.notes")]
+    #[case("http://x.org/b", None, "http%3A%2F%2Fx.org%2Fb")]
+    #[case("/etc/passwd", None, "%2Fetc%2Fpasswd")]
+    #[case("..", Some("bookmarks"), "%2E..bookmarks")]
+    fn it_keeps_identifiers_in_the_state_dir(
+        #[case] identifier: &str,
+        #[case] extension: Option<&str>,
+        #[case] name: &str,
+    ) {
+        let dir = Path::new("state");
+
+        check!(document_path(dir, identifier, extension) == dir.join(name));
+    }
+
+    #[rstest]
+    fn it_reads_the_legacy_state() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let (state_dir, legacy_dir) = (dir.join("state"), dir.join("legacy"));
+        std::fs::create_dir_all(&state_dir).unwrap();
+        std::fs::create_dir_all(&legacy_dir).unwrap();
+        std::fs::write(legacy_dir.join("urn:isbn:1.notes"), "old").unwrap();
+
+        let read = |id| read_document_from(&state_dir, &legacy_dir, id, Some("notes"));
+        check!(read("urn:isbn:1").as_deref() == Some("old"));
+        check!(read("../legacy/urn:isbn:1") == None);
+        std::fs::write(
+            document_path(&state_dir, "urn:isbn:1", Some("notes")),
+            "new",
+        )
+        .unwrap();
+        check!(read("urn:isbn:1").as_deref() == Some("new"));
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::anyhow;
+use ratatui::style::{Color, Modifier, Style};
+
+/// Styles of everything drawn while reading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Text and background of the panes.
+    pub text: Style,
+    /// The pivot letter of the current word.
+    pub pivot: Style,
+    /// The words being read, in the content.
+    pub cursor: Style,
+    /// The selected entry of the table of contents and of the lists.
+    pub selection: Style,
+    /// Secondary lines of the lists, like the chapter of a bookmark, and table headers.
+    pub heading: Style,
+    /// Highlighted passages.
+    pub highlight: Style,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    #[default]
+    Dark,
+    Light,
+    Solarized,
+    HighContrast,
+    /// Only bold, reversed and underlined text, used when `NO_COLOR` is set.
+    Monochrome,
+}
+
+impl Theme {
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Dark => Self {
+                text: Style::default().fg(Color::White).bg(Color::Black),
+                pivot: Style::default().fg(Color::Red),
+                cursor: Style::default().bg(Color::LightYellow),
+                selection: Style::default().bg(Color::Yellow).fg(Color::Black),
+                heading: Style::default().fg(Color::Yellow),
+                highlight: Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::UNDERLINED),
+            },
+            Preset::Light => Self {
+                text: Style::default().fg(Color::Black).bg(Color::White),
+                pivot: Style::default().fg(Color::Red),
+                cursor: Style::default().bg(Color::LightYellow).fg(Color::Black),
+                selection: Style::default().bg(Color::Blue).fg(Color::White),
+                heading: Style::default().fg(Color::Blue),
+                highlight: Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::UNDERLINED),
+            },
+            Preset::Solarized => {
+                let base03 = Color::Rgb(0x00, 0x2b, 0x36);
+                let yellow = Color::Rgb(0xb5, 0x89, 0x00);
+                Self {
+                    text: Style::default().fg(Color::Rgb(0x83, 0x94, 0x96)).bg(base03),
+                    pivot: Style::default().fg(Color::Rgb(0xdc, 0x32, 0x2f)),
+                    cursor: Style::default().bg(yellow).fg(base03),
+                    selection: Style::default().bg(Color::Rgb(0x26, 0x8b, 0xd2)).fg(base03),
+                    heading: Style::default().fg(yellow),
+                    highlight: Style::default()
+                        .fg(Color::Rgb(0x2a, 0xa1, 0x98))
+                        .add_modifier(Modifier::UNDERLINED),
+                }
+            }
+            Preset::HighContrast => Self {
+                text: Style::default().fg(Color::White).bg(Color::Black),
+                pivot: Style::default()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+                cursor: Style::default().bg(Color::White).fg(Color::Black),
+                selection: Style::default()
+                    .bg(Color::White)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+                heading: Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                highlight: Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            },
+            Preset::Monochrome => Self {
+                text: Style::default(),
+                pivot: Style::default().add_modifier(Modifier::BOLD),
+                cursor: Style::default().add_modifier(Modifier::REVERSED),
+                selection: Style::default().add_modifier(Modifier::REVERSED),
+                heading: Style::default().add_modifier(Modifier::BOLD),
+                highlight: Style::default().add_modifier(Modifier::UNDERLINED),
+            },
+        }
+    }
+}
+
+/// The `[theme]` settings: a preset, with some of its colors replaced.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    preset: Preset,
+    foreground: Option<String>,
+    background: Option<String>,
+    pivot: Option<String>,
+    cursor: Option<String>,
+    selection: Option<String>,
+    heading: Option<String>,
+    highlight: Option<String>,
+}
+
+impl ThemeConfig {
+    pub fn theme(&self) -> anyhow::Result<Theme> {
+        let mut theme = Theme::preset(self.preset);
+        let color = |color: &Option<String>| {
+            color
+                .as_deref()
+                .map(|c| c.parse().map_err(|_| anyhow!("unknown color `{c}`")))
+                .transpose()
+        };
+        if let Some(fg) = color(&self.foreground)? {
+            theme.text = theme.text.fg(fg);
+        }
+        if let Some(bg) = color(&self.background)? {
+            theme.text = theme.text.bg(bg);
+        }
+        if let Some(fg) = color(&self.pivot)? {
+            theme.pivot = theme.pivot.fg(fg);
+        }
+        if let Some(bg) = color(&self.cursor)? {
+            theme.cursor = theme.cursor.bg(bg);
+        }
+        if let Some(bg) = color(&self.selection)? {
+            theme.selection = theme.selection.bg(bg);
+        }
+        if let Some(fg) = color(&self.heading)? {
+            theme.heading = theme.heading.fg(fg);
+        }
+        if let Some(fg) = color(&self.highlight)? {
+            theme.highlight = theme.highlight.fg(fg);
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    fn it_replaces_preset_colors() {
+        let config: ThemeConfig = toml::from_str(
+            r##"
+            preset = "light"
+            pivot = "magenta"
+            background = "#fdf6e3"
+            "##,
+        )
+        .unwrap();
+
+        let theme = config.theme().unwrap();
+        check!(theme.pivot == Style::default().fg(Color::Magenta));
+        check!(theme.text.bg == Some(Color::Rgb(0xfd, 0xf6, 0xe3)));
+        check!(theme.text.fg == Some(Color::Black));
+        check!(theme.selection == Theme::preset(Preset::Light).selection);
+    }
+
+    #[rstest]
+    #[case(r#"preset = "sepia""#)]
+    #[case(r#"pivot = "reddish""#)]
+    fn it_rejects_unknown_themes(#[case] config: &str) {
+        let theme = toml::from_str::<ThemeConfig>(config)
+            .map_err(anyhow::Error::from)
+            .and_then(|c| c.theme());
+        check!(theme.is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crate::document::is_cjk;
+
+/// Number of words the average reading speed is computed over.
+const PACE_WINDOW: usize = 100;
+
+/// Letters a word can have before it is shown longer.
+const LONG_WORD: usize = 8;
+
+/// Share of a short word a Chinese or Japanese character is shown for, since words are
+/// split down to single ideographs.
+const CJK_CHAR: f64 = 0.5;
+
+/// The break that follows a word.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pause {
+    None,
+    Paragraph,
+    Section,
+}
+
+/// Extra display time for the words that end a paragraph or a section, and the slower
+/// start of reading after a pause or a new section.
+pub struct Timing {
+    pub paragraph_pause: Duration,
+    pub section_pause: Duration,
+    /// Number of words over which the speed ramps up.
+    pub ramp_words: usize,
+    /// Fraction of the speed the ramp starts at.
+    pub ramp_start: f64,
+}
+
+impl Timing {
+    /// How long a chunk of words stays on screen, `base` being the duration of a short
+    /// plain word. Past the last word, the empty chunk lasts as long as a short word.
+    pub fn word_duration(&self, words: &[&str], base: Duration, pause: Pause) -> Duration {
+        let factor = if words.is_empty() {
+            1.0
+        } else {
+            words.iter().map(|w| word_factor(w)).sum()
+        };
+        base.mul_f64(factor) + self.pause(pause)
+    }
+
+    pub fn pause(&self, pause: Pause) -> Duration {
+        match pause {
+            Pause::None => Duration::ZERO,
+            Pause::Paragraph => self.paragraph_pause,
+            Pause::Section => self.section_pause,
+        }
+    }
+
+    /// Fraction of the speed to read at with `remaining` words left in the ramp, if the
+    /// ramp is still going.
+    pub fn ramp_rate(&self, remaining: usize) -> Option<f64> {
+        if remaining == 0 || self.ramp_words == 0 {
+            return None;
+        }
+        let progress =
+            (self.ramp_words - remaining.min(self.ramp_words)) as f64 / self.ramp_words as f64;
+        Some(self.ramp_start + (1.0 - self.ramp_start) * progress)
+    }
+
+    /// The base duration of a word with `remaining` words left in the ramp.
+    pub fn ramp(&self, base: Duration, remaining: usize) -> Duration {
+        self.ramp_rate(remaining)
+            .map_or(base, |rate| base.div_f64(rate))
+    }
+}
+
+/// How many times longer than a short plain word a word is shown: sentence and clause
+/// endings give time to take in what was read, long words and numbers take longer to
+/// recognize.
+fn word_factor(word: &str) -> f64 {
+    let cjk = word.chars().filter(|c| is_cjk(*c)).count();
+    let mut factor = if cjk > 0 { cjk as f64 * CJK_CHAR } else { 1.0 };
+    let end = word.trim_end_matches(['"', '\'', ')', ']', '»', '”', '’', '」', '』']);
+    if end.ends_with(['.', '!', '?', '…', '。', '！', '？']) {
+        factor += 1.0;
+    } else if end.ends_with([',', ';', ':', '—', '–', '、', '，', '；', '：']) {
+        factor += 0.5;
+    }
+    let letters = word
+        .chars()
+        .filter(|c| c.is_alphanumeric() && !is_cjk(*c))
+        .count();
+    if letters > LONG_WORD {
+        factor += ((letters - LONG_WORD) as f64 * 0.1).min(1.0);
+    }
+    if word.chars().any(|c| c.is_numeric()) {
+        factor += 0.5;
+    }
+    factor
+}
+
+/// The durations of the last chunks shown with their number of words, giving the actual
+/// reading speed.
+#[derive(Default)]
+pub struct Pace {
+    durations: VecDeque<(Duration, usize)>,
+    words: usize,
+}
+
+impl Pace {
+    pub fn record(&mut self, duration: Duration, words: usize) {
+        self.durations.push_back((duration, words));
+        self.words += words;
+        while self.words - self.durations[0].1 >= PACE_WINDOW {
+            let (_, words) = self.durations.pop_front().unwrap();
+            self.words -= words;
+        }
+    }
+
+    /// Average words per minute, if any word was recorded.
+    pub fn wpm(&self) -> Option<u128> {
+        let total: Duration = self.durations.iter().map(|(d, _)| d).sum();
+        (!total.is_zero()).then(|| 60000 * self.words as u128 / total.as_millis().max(1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert2::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("plain", 100)]
+    #[case("end.\"", 200)]
+    #[case("clause,", 150)]
+    #[case("so—", 150)]
+    #[case("1984", 150)]
+    #[case("comprehension", 150)]
+    #[case("速", 50)]
+    #[case("読む", 100)]
+    #[case("能。", 150)]
+    fn it_shows_words_longer_by_kind(timing: Timing, #[case] word: &str, #[case] millis: u64) {
+        let duration = timing.word_duration(&[word], Duration::from_millis(100), Pause::None);
+        check!(duration == Duration::from_millis(millis));
+    }
+
+    #[rstest]
+    fn it_times_chunks_word_by_word(timing: Timing) {
+        let base = Duration::from_millis(100);
+        let duration = timing.word_duration(&["reading", "the", "book."], base, Pause::None);
+        check!(duration == Duration::from_millis(400));
+        check!(timing.word_duration(&[], base, Pause::None) == base);
+    }
+
+    #[rstest]
+    fn it_pauses_at_boundaries(timing: Timing) {
+        let base = Duration::from_millis(100);
+        check!(
+            timing.word_duration(&["word"], base, Pause::Paragraph) == Duration::from_millis(400)
+        );
+        check!(
+            timing.word_duration(&["word"], base, Pause::Section) == Duration::from_millis(1100)
+        );
+    }
+
+    #[rstest]
+    fn it_ramps_up_the_speed(timing: Timing) {
+        let base = Duration::from_millis(100);
+        check!(timing.ramp(base, 4) == Duration::from_millis(200));
+        check!(timing.ramp(base, 3) == Duration::from_millis(160));
+        check!(timing.ramp_rate(1) == Some(0.875));
+        check!(timing.ramp(base, 0) == base);
+    }
+
+    #[rstest]
+    fn it_averages_the_pace() {
+        let mut pace = Pace::default();
+        check!(pace.wpm() == None);
+
+        pace.record(Duration::from_millis(200), 1);
+        pace.record(Duration::from_millis(400), 1);
+        check!(pace.wpm() == Some(200));
+        pace.record(Duration::from_millis(600), 4);
+        check!(pace.wpm() == Some(300));
+    }
+
+    #[fixture]
+    fn timing() -> Timing {
+        Timing {
+            paragraph_pause: Duration::from_millis(300),
+            section_pause: Duration::from_millis(1000),
+            ramp_words: 4,
+            ramp_start: 0.5,
+        }
+    }
+}